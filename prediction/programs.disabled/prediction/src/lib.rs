@@ -1,7 +1,80 @@
+// Only for anchor 0.31's generated IDL resize handler, which still calls
+// AccountInfo::realloc (deprecated in solana-account-info 2.3). `#[program]`
+// emits it into a `__private` module at the crate root, even with `no-idl`, so
+// no narrower item can carry the allow. Drop this with the anchor upgrade that
+// switches the handler to `resize`; nothing in this file calls a deprecated API.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn, Transfer};
+use anchor_lang::solana_program::address_lookup_table::{instruction as alt_instruction, program as alt_program};
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions::{self as instructions_sysvar, get_instruction_relative};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, MintTo, Burn, Transfer, CloseAccount};
+use agent_registry::program::AgentRegistry;
+use agent_registry::{Agent, ProofRequest, Registry, ResolutionRound, Verdict};
+
+#[cfg(all(feature = "devnet", feature = "mainnet"))]
+compile_error!("features `devnet` and `mainnet` are mutually exclusive");
 
+// Localnet builds use the devnet program id from Anchor.toml.
+#[cfg(not(feature = "mainnet"))]
 declare_id!("66wZsPVBASArR5zZ77PpHACecUpyD3Jc97BcKq2aUy9m");
+// Mainnet reuses the devnet deploy key until a dedicated one is generated.
+#[cfg(feature = "mainnet")]
+declare_id!("66wZsPVBASArR5zZ77PpHACecUpyD3Jc97BcKq2aUy9m");
+
+const MAX_QUESTION: usize = 200;
+const MAX_TRANSCRIPT_URI: usize = 200;
+const MARKETS_PER_PAGE: usize = 32;
+const MAX_REBALANCE_LEGS: usize = 4;
+const MAX_TRANSLATIONS: usize = 8;
+const MAX_COMMITTEE: usize = 8;
+const MAX_LANG_CODE: usize = 8;
+const MAX_MERKLE_PROOF: usize = 24;
+const RESOLUTION_PAYLOAD_VERSION: u8 = 1;
+const RESOLUTION_PAYLOAD_LEN: usize = 1 + 32 + 8 + 1 + 32 + 8;
+// Relayer tip cap for claim_for, as a share of the claimed amount
+const MAX_RELAYER_TIP_BPS: u64 = 500;
+const MAX_NOTE_URI: usize = 200;
+const MAX_RESOLUTION_NOTES: usize = 64;
+// Payout references are carried as SPL memos, which partners match on
+const MAX_PAYOUT_REFERENCE: usize = 64;
+const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+// claim_many: remaining accounts per market, and markets per call
+const CLAIM_MANY_GROUP: usize = 5;
+const MAX_CLAIM_MANY: usize = 8;
+// Smallest position (outcome token base units) that can be wrapped into a receipt
+const POSITION_RECEIPT_MIN: u64 = 1_000_000_000;
+#[cfg(not(feature = "mainnet"))]
+const FAUCET_MAX_AMOUNT: u64 = 1_000_000_000;
+#[cfg(not(feature = "mainnet"))]
+const FAUCET_LAMPORTS: u64 = 10_000_000;
+// Resolving against a side holding more than this share of supply is treated as contested
+const LOPSIDED_SUPPLY_BPS: u128 = 9_000;
+const CONTESTED_DISPUTE_SECS: i64 = 24 * 60 * 60;
+// Furthest a resolver may push claim_enabled_at past the resolution time
+const MAX_CLAIM_DELAY_SECS: i64 = 30 * 24 * 60 * 60;
+// A market with nothing outstanding may be garbage collected this long after it ends
+const GC_GRACE_SECS: i64 = 7 * 24 * 60 * 60;
+// After end_time, a committed agent has this long to reveal before other resolution paths reopen
+const REVEAL_WINDOW_SECS: i64 = 60 * 60;
+// Question templates: placeholders per template and length of each name
+const MAX_TEMPLATE_VARS: usize = 8;
+const MAX_TEMPLATE_VAR_NAME: usize = 16;
+// Trades between two automatic MarketStateCheckpoint events
+const CHECKPOINT_INTERVAL: u64 = 64;
+
+/// Capability bits reported by `program_info`
+pub const FEATURE_FAUCET: u32 = 1 << 0;
+pub const FEATURE_DEVNET: u32 = 1 << 1;
+pub const FEATURE_MAINNET: u32 = 1 << 2;
+const FEATURE_FLAGS: u32 = if cfg!(feature = "mainnet") { FEATURE_MAINNET } else { FEATURE_FAUCET }
+    | if cfg!(feature = "devnet") { FEATURE_DEVNET } else { 0 };
 
 #[program]
 pub mod prediction_market {
@@ -13,41 +86,242 @@ pub mod prediction_market {
         end_time: i64,
         market_id: u64,
         bump: u8,
+        resolution_mode: ResolutionMode,
     ) -> Result<()> {
-        let market = &mut ctx.accounts.market;
-        
-        require!(question.len() <= 200, ErrorCode::QuestionTooLong);
-        require!(end_time > Clock::get()?.unix_timestamp, ErrorCode::InvalidEndTime);
+        ctx.accounts.init_market(&ctx.bumps, question, end_time, market_id, bump, resolution_mode)
+    }
 
-        market.authority = ctx.accounts.authority.key();
-        market.market_id = market_id;
-        market.question = question;
-        market.yes_mint = ctx.accounts.yes_mint.key();
-        market.no_mint = ctx.accounts.no_mint.key();
-        market.collateral_vault = ctx.accounts.collateral_vault.key();
-        market.end_time = end_time;
-        market.is_resolved = false;
-        market.winning_outcome = None;
-        market.total_yes_supply = 0;
-        market.total_no_supply = 0;
-        market.bump = bump;
+    /// One-shot creation of a follow-up market about an agent's fulfilled
+    /// proof request; the market is linked to that agent, whose authority or
+    /// an operator must create it.
+    pub fn create_market_from_request(
+        ctx: Context<CreateMarketFromRequest>,
+        question: String,
+        end_time: i64,
+        market_id: u64,
+        bump: u8,
+        resolution_mode: ResolutionMode,
+    ) -> Result<()> {
+        let request = &ctx.accounts.proof_request;
+        // Slashed requests are also marked fulfilled, but carry no proof
+        require!(
+            request.fulfilled && !request.proof_uri.is_empty(),
+            ErrorCode::RequestNotFulfilled
+        );
+
+        ctx.accounts
+            .create
+            .init_market(&ctx.bumps.create, question, end_time, market_id, bump, resolution_mode)?;
+        let market = &mut ctx.accounts.create.market;
+        market.agent = Some(request.agent);
+        let max_open_markets = ctx.accounts.registry.tier(ctx.accounts.agent.tier).max_open_markets;
+        ctx.accounts.agent_markets.append(
+            &mut ctx.accounts.agent_markets_page,
+            request.agent,
+            market.key(),
+            max_open_markets,
+            ctx.bumps.agent_markets,
+            ctx.bumps.agent_markets_page,
+        )?;
+
+        emit!(MarketCreatedFromRequest {
+            market: market.key(),
+            agent: request.agent,
+            request: request.key(),
+            source_market_id: request.market_id,
+        });
+
+        Ok(())
+    }
+
+    /// New market reusing an existing one's collateral, agent link and
+    /// resolution mode. The question must still be new (the question index is
+    /// unique), e.g. the next round of a recurring matchup.
+    pub fn clone_market(
+        ctx: Context<CloneMarket>,
+        question: String,
+        end_time: i64,
+        market_id: u64,
+        bump: u8,
+    ) -> Result<()> {
+        let source = &ctx.accounts.source_market;
+        let resolution_mode = source.resolution_mode;
+        let agent = source.agent;
+        let min_trade_amount = source.min_trade_amount;
+
+        ctx.accounts
+            .create
+            .init_market(&ctx.bumps.create, question, end_time, market_id, bump, resolution_mode)?;
+        let market = &mut ctx.accounts.create.market;
+        market.agent = agent;
+        market.min_trade_amount = min_trade_amount;
+        if let Some(agent) = agent {
+            let (Some(agent_markets), Some(page), Some(registry), Some(agent_account)) = (
+                ctx.accounts.agent_markets.as_deref_mut(),
+                ctx.accounts.agent_markets_page.as_deref_mut(),
+                ctx.accounts.registry.as_deref(),
+                ctx.accounts.agent.as_deref(),
+            ) else {
+                return err!(ErrorCode::AgentMarketsRequired);
+            };
+            let (Some(index_bump), Some(page_bump)) =
+                (ctx.bumps.agent_markets, ctx.bumps.agent_markets_page)
+            else {
+                return err!(ErrorCode::AgentMarketsRequired);
+            };
+            let max_open_markets = registry.tier(agent_account.tier).max_open_markets;
+            agent_markets.append(page, agent, market.key(), max_open_markets, index_bump, page_bump)?;
+        }
+
+        emit!(MarketCloned {
+            market: market.key(),
+            source_market: ctx.accounts.source_market.key(),
+            end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Schedule a battle between two registered agents and open its market in
+    /// the same instruction. The market's id is the battle id, so
+    /// `["battle", authority, battle_id]` and
+    /// `["market", authority, battle_id]` derive from the same inputs.
+    pub fn schedule_battle(
+        ctx: Context<ScheduleBattle>,
+        question: String,
+        end_time: i64,
+        battle_id: u64,
+        bump: u8,
+        resolution_mode: ResolutionMode,
+    ) -> Result<()> {
+        let agent_a = ctx.accounts.agent_a.key();
+        let agent_b = ctx.accounts.agent_b.key();
+        require_keys_neq!(agent_a, agent_b, ErrorCode::InvalidBattle);
+
+        ctx.accounts
+            .create
+            .init_market(&ctx.bumps.create, question, end_time, battle_id, bump, resolution_mode)?;
+
+        let battle = &mut ctx.accounts.battle;
+        battle.authority = ctx.accounts.create.authority.key();
+        battle.battle_id = battle_id;
+        battle.agent_a = agent_a;
+        battle.agent_b = agent_b;
+        battle.market = ctx.accounts.create.market.key();
+        battle.scheduled_at = Clock::get()?.unix_timestamp;
+        battle.bump = ctx.bumps.battle;
+
+        emit!(BattleScheduled {
+            battle: battle.key(),
+            battle_id,
+            agent_a,
+            agent_b,
+            market: battle.market,
+        });
+
+        Ok(())
+    }
+
+    /// Register a question template such as
+    /// "Will {agent_a} beat {agent_b} in round {n}?". `variables` names every
+    /// placeholder; markets created from it fill them in that order.
+    pub fn create_template(
+        ctx: Context<CreateTemplate>,
+        template_id: u64,
+        body: String,
+        variables: Vec<String>,
+    ) -> Result<()> {
+        require!(body.len() <= MAX_QUESTION, ErrorCode::QuestionTooLong);
+        require!(is_valid_statement(&body), ErrorCode::InvalidStatement);
+        require!(variables.len() <= MAX_TEMPLATE_VARS, ErrorCode::InvalidTemplate);
+        for (i, name) in variables.iter().enumerate() {
+            require!(
+                !name.is_empty()
+                    && name.len() <= MAX_TEMPLATE_VAR_NAME
+                    && !name.contains(['{', '}'])
+                    && !variables[..i].contains(name),
+                ErrorCode::InvalidTemplate
+            );
+        }
+        // Rendering with the names themselves checks every placeholder is declared
+        require!(render_template(&body, &variables, &variables).is_some(), ErrorCode::InvalidTemplate);
+
+        let template = &mut ctx.accounts.template;
+        template.authority = ctx.accounts.authority.key();
+        template.template_id = template_id;
+        template.body = body;
+        template.variables = variables;
+        template.bump = ctx.bumps.template;
+
+        emit!(TemplateCreated {
+            template: template.key(),
+            authority: template.authority,
+            template_id,
+        });
+
+        Ok(())
+    }
+
+    /// Create a market whose question is a template filled with `values`. The
+    /// full question is still passed, since it seeds the market index, and
+    /// must be exactly what the template renders to, so every round of a
+    /// series carries the same wording.
+    pub fn create_from_template(
+        ctx: Context<CreateFromTemplate>,
+        question: String,
+        end_time: i64,
+        market_id: u64,
+        bump: u8,
+        resolution_mode: ResolutionMode,
+        values: Vec<String>,
+    ) -> Result<()> {
+        let template = &ctx.accounts.template;
+        let rendered = render_template(&template.body, &template.variables, &values)
+            .ok_or(ErrorCode::TemplateMismatch)?;
+        require!(rendered == question, ErrorCode::TemplateMismatch);
+
+        ctx.accounts
+            .create
+            .init_market(&ctx.bumps.create, question, end_time, market_id, bump, resolution_mode)?;
+
+        emit!(MarketCreatedFromTemplate {
+            market: ctx.accounts.create.market.key(),
+            template: template.key(),
+            values,
+        });
 
         Ok(())
     }
 
+    /// Returns the outcome tokens minted. Like every value-returning
+    /// instruction here, the result is set as return data: a borsh u64
+    /// (8 bytes, little-endian), readable by CPI callers via get_return_data.
+    /// `frontend_id` attributes the trade to a partner UI in the event.
     pub fn buy_tokens(
         ctx: Context<BuyTokens>,
         amount: u64,
         outcome: Outcome,
-    ) -> Result<()> {
+        frontend_id: Option<u16>,
+    ) -> Result<u64> {
+        require!(!ctx.accounts.config.disable_trade, ErrorCode::InstructionPaused);
+        let user = ctx.accounts.user.key();
         let market = &mut ctx.accounts.market;
+        let market_key = market.key();
+        let clock = Clock::get()?;
         
-        require!(!market.is_resolved, ErrorCode::MarketResolved);
-        require!(
-            Clock::get()?.unix_timestamp < market.end_time,
-            ErrorCode::MarketEnded
-        );
-        require!(amount > 0, ErrorCode::InvalidAmount);
+        market
+            .check_trade(ctx.accounts.allowlist_pass.as_deref(), clock.unix_timestamp, amount)
+            .map_err(|e| trade_rejected(e, market_key, user, outcome, amount, true))?;
+        if market.block_round_trips {
+            let trader_state = ctx
+                .accounts
+                .trader_state
+                .as_deref_mut()
+                .ok_or(ErrorCode::TraderStateRequired)?;
+            trader_state
+                .record_trade(clock.slot, outcome, true)
+                .map_err(|e| trade_rejected(e, market_key, user, outcome, amount, true))?;
+        }
 
         // Transfer collateral from user to vault
         let cpi_accounts = Transfer {
@@ -97,24 +371,47 @@ pub mod prediction_market {
             user: ctx.accounts.user.key(),
             outcome,
             amount,
+            frontend_id,
         });
 
-        Ok(())
+        if market.record_trades(1, amount)? {
+            ctx.accounts.collateral_vault.reload()?;
+            emit!(market.checkpoint(market_key, ctx.accounts.collateral_vault.amount, clock.slot));
+        }
+
+        Ok(amount)
     }
 
+    /// Returns the collateral paid out (borsh u64 return data).
     pub fn sell_tokens(
         ctx: Context<SellTokens>,
         amount: u64,
         outcome: Outcome,
-    ) -> Result<()> {
+        frontend_id: Option<u16>,
+    ) -> Result<u64> {
+        let user = ctx.accounts.user.key();
         let market = &mut ctx.accounts.market;
+        let market_key = market.key();
+        let clock = Clock::get()?;
         
-        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        market
+            .check_trade(ctx.accounts.allowlist_pass.as_deref(), clock.unix_timestamp, amount)
+            .map_err(|e| trade_rejected(e, market_key, user, outcome, amount, false))?;
+        if market.block_round_trips {
+            let trader_state = ctx
+                .accounts
+                .trader_state
+                .as_deref_mut()
+                .ok_or(ErrorCode::TraderStateRequired)?;
+            trader_state
+                .record_trade(clock.slot, outcome, false)
+                .map_err(|e| trade_rejected(e, market_key, user, outcome, amount, false))?;
+        }
+        // Accounting can drift from the real vault; never promise more than it holds
         require!(
-            Clock::get()?.unix_timestamp < market.end_time,
-            ErrorCode::MarketEnded
+            ctx.accounts.collateral_vault.amount >= amount,
+            ErrorCode::ReserveVaultMismatch
         );
-        require!(amount > 0, ErrorCode::InvalidAmount);
 
         // Burn outcome tokens from user
         match outcome {
@@ -164,71 +461,222 @@ pub mod prediction_market {
             user: ctx.accounts.user.key(),
             outcome,
             amount,
+            frontend_id,
+        });
+
+        if market.record_trades(1, amount)? {
+            ctx.accounts.collateral_vault.reload()?;
+            emit!(market.checkpoint(market_key, ctx.accounts.collateral_vault.amount, clock.slot));
+        }
+
+        Ok(amount)
+    }
+
+    /// Several buy/sell legs against one market in a single call. Legs run in
+    /// order, so a later leg may sell tokens an earlier leg bought; collateral
+    /// is netted and moved once at the end.
+    pub fn mm_rebalance(ctx: Context<MmRebalance>, legs: Vec<RebalanceLeg>) -> Result<()> {
+        require!(
+            !ctx.accounts.config.disable_trade || legs.iter().all(|leg| !leg.is_buy),
+            ErrorCode::InstructionPaused
+        );
+        let user = ctx.accounts.user.key();
+        let market = &mut ctx.accounts.market;
+        let market_key = market.key();
+        let pass = ctx.accounts.allowlist_pass.as_deref();
+        require!(
+            !legs.is_empty() && legs.len() <= MAX_REBALANCE_LEGS,
+            ErrorCode::InvalidLegCount
+        );
+
+        // Copied out so the signer seeds don't pin a borrow of the market
+        let market_authority = market.authority;
+        let market_id = market.market_id.to_le_bytes();
+        let seeds = &[
+            b"market".as_ref(),
+            market_authority.as_ref(),
+            &market_id,
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let clock = Clock::get()?;
+        let mut trader_state = if market.block_round_trips {
+            Some(
+                ctx.accounts
+                    .trader_state
+                    .as_deref_mut()
+                    .ok_or(ErrorCode::TraderStateRequired)?,
+            )
+        } else {
+            None
+        };
+
+        let mut collateral_in: u64 = 0;
+        let mut collateral_out: u64 = 0;
+
+        for leg in &legs {
+            // Same checks as buy_tokens/sell_tokens, so market-maker fills can't skip one
+            let rejected = |e| trade_rejected(e, market_key, user, leg.outcome, leg.amount, leg.is_buy);
+            market
+                .check_trade(pass, clock.unix_timestamp, leg.amount)
+                .map_err(rejected)?;
+            if let Some(trader_state) = trader_state.as_deref_mut() {
+                trader_state
+                    .record_trade(clock.slot, leg.outcome, leg.is_buy)
+                    .map_err(rejected)?;
+            }
+
+            let (mint, user_account) = match leg.outcome {
+                Outcome::Yes => (&ctx.accounts.yes_mint, &ctx.accounts.user_yes_account),
+                Outcome::No => (&ctx.accounts.no_mint, &ctx.accounts.user_no_account),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+
+            if leg.is_buy {
+                let cpi_accounts = MintTo {
+                    mint: mint.to_account_info(),
+                    to: user_account.to_account_info(),
+                    authority: market.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+                token::mint_to(cpi_ctx, leg.amount)?;
+                collateral_in = collateral_in
+                    .checked_add(leg.amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            } else {
+                let cpi_accounts = Burn {
+                    mint: mint.to_account_info(),
+                    from: user_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+                token::burn(cpi_ctx, leg.amount)?;
+                collateral_out = collateral_out
+                    .checked_add(leg.amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+
+            let supply = match leg.outcome {
+                Outcome::Yes => &mut market.total_yes_supply,
+                Outcome::No => &mut market.total_no_supply,
+            };
+            *supply = if leg.is_buy {
+                supply.checked_add(leg.amount)
+            } else {
+                supply.checked_sub(leg.amount)
+            }
+            .ok_or(ErrorCode::Overflow)?;
+        }
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        if collateral_in > collateral_out {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.user_collateral.to_account_info(),
+                to: ctx.accounts.collateral_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, collateral_in - collateral_out)?;
+        } else if collateral_out > collateral_in {
+            let payout = collateral_out - collateral_in;
+            require!(
+                ctx.accounts.collateral_vault.amount >= payout,
+                ErrorCode::ReserveVaultMismatch
+            );
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                to: ctx.accounts.user_collateral.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, payout)?;
+        }
+
+        let volume = collateral_in.checked_add(collateral_out).ok_or(ErrorCode::Overflow)?;
+        let checkpoint_due = market.record_trades(legs.len() as u64, volume)?;
+        emit!(MarketRebalanced {
+            market: market.key(),
+            user: ctx.accounts.user.key(),
+            legs,
+            collateral_in,
+            collateral_out,
         });
 
+        if checkpoint_due {
+            ctx.accounts.collateral_vault.reload()?;
+            emit!(market.checkpoint(market.key(), ctx.accounts.collateral_vault.amount, clock.slot));
+        }
+
         Ok(())
     }
 
     pub fn resolve_market(
         ctx: Context<ResolveMarket>,
         winning_outcome: Outcome,
+        transcript_hash: [u8; 32],
+        transcript_uri: Option<String>,
+        claim_enabled_at: Option<i64>,
     ) -> Result<()> {
+        require!(!ctx.accounts.config.disable_resolve, ErrorCode::InstructionPaused);
         let market = &mut ctx.accounts.market;
+        let transcript_uri = transcript_uri.unwrap_or_default();
         
+        require!(transcript_uri.len() <= MAX_TRANSCRIPT_URI, ErrorCode::TranscriptUriTooLong);
         require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
         require!(
             Clock::get()?.unix_timestamp >= market.end_time,
             ErrorCode::MarketNotEnded
         );
         require!(
-            ctx.accounts.authority.key() == market.authority,
-            ErrorCode::Unauthorized
+            !market.awaiting_reveal(Clock::get()?.unix_timestamp),
+            ErrorCode::AwaitingReveal
         );
 
-        market.is_resolved = true;
-        market.winning_outcome = Some(winning_outcome);
+        // Claims may be held back (e.g. for a dispute window) independently of
+        // end_time, but not indefinitely
+        let claim_enabled_at = claim_enabled_at.unwrap_or(0);
+        let latest_claim_at = Clock::get()?
+            .unix_timestamp
+            .checked_add(MAX_CLAIM_DELAY_SECS)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(claim_enabled_at <= latest_claim_at, ErrorCode::ClaimDelayTooLong);
+        market.resolve_from(
+            &AuthorityDecision(winning_outcome),
+            claim_enabled_at,
+            Clock::get()?.unix_timestamp,
+        )?;
+        ctx.accounts.market_list.delist(&mut ctx.accounts.market_list_page, market.key())?;
+        release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+        // Bind the outcome to the battle transcript the agents produced
+        market.transcript_hash = transcript_hash;
+        market.transcript_uri = transcript_uri;
 
         emit!(MarketResolved {
             market: market.key(),
             winning_outcome,
+            transcript_hash,
+            transcript_uri: market.transcript_uri.clone(),
+            claim_enabled_at: market.claim_enabled_at,
         });
 
         Ok(())
     }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, amount: u64) -> Result<()> {
+    /// Open (or, after a round that ended without an outcome, reopen) the
+    /// registry resolution round of an `AgentConsensus` market. Only the
+    /// market authority may, and voting must stay open past `end_time`; the
+    /// market PDA signs so the registry knows the round is genuine.
+    pub fn open_agent_round(ctx: Context<OpenAgentRound>, window_end_ts: i64) -> Result<()> {
         let market = &ctx.accounts.market;
-        
-        require!(market.is_resolved, ErrorCode::MarketNotResolved);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-
-        let winning_outcome = market.winning_outcome.unwrap();
-
-        // Burn winning tokens
-        match winning_outcome {
-            Outcome::Yes => {
-                let cpi_accounts = Burn {
-                    mint: ctx.accounts.yes_mint.to_account_info(),
-                    from: ctx.accounts.user_yes_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                };
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-                token::burn(cpi_ctx, amount)?;
-            }
-            Outcome::No => {
-                let cpi_accounts = Burn {
-                    mint: ctx.accounts.no_mint.to_account_info(),
-                    from: ctx.accounts.user_no_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                };
-                let cpi_program = ctx.accounts.token_program.to_account_info();
-                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-                token::burn(cpi_ctx, amount)?;
-            }
-        }
+        require!(
+            market.resolution_mode == ResolutionMode::AgentConsensus,
+            ErrorCode::WrongResolutionMode
+        );
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        require!(window_end_ts > market.end_time, ErrorCode::InvalidRoundWindow);
 
-        // Transfer collateral to winner
         let seeds = &[
             b"market".as_ref(),
             market.authority.as_ref(),
@@ -237,220 +685,3823 @@ pub mod prediction_market {
         ];
         let signer = &[&seeds[..]];
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.collateral_vault.to_account_info(),
-            to: ctx.accounts.user_collateral.to_account_info(),
-            authority: market.to_account_info(),
+        let cpi_accounts = agent_registry::cpi::accounts::OpenResolutionRound {
+            round: ctx.accounts.round.to_account_info(),
+            market: market.to_account_info(),
+            payer: ctx.accounts.authority.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_program = ctx.accounts.registry_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::transfer(cpi_ctx, amount)?;
+        agent_registry::cpi::open_resolution_round(cpi_ctx, window_end_ts)
+    }
 
-        emit!(WinningsClaimed {
-            user: ctx.accounts.user.key(),
-            amount,
+    /// Resolve an `AgentConsensus` market from its finalized registry round.
+    /// Permissionless: the outcome comes entirely from the round.
+    pub fn resolve_from_agents(ctx: Context<ResolveFromAgents>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        let round = &ctx.accounts.round;
+
+        // Permissionless cranks get retried; the outcome is fixed by the round
+        // either way, so a repeat call is a no-op rather than an error
+        if market.is_resolved {
+            return Ok(());
+        }
+        require!(!ctx.accounts.config.disable_resolve, ErrorCode::InstructionPaused);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(
+            Clock::get()?.unix_timestamp >= market.end_time,
+            ErrorCode::MarketNotEnded
+        );
+        require!(
+            !market.awaiting_reveal(Clock::get()?.unix_timestamp),
+            ErrorCode::AwaitingReveal
+        );
+
+        let winning_outcome = market.resolve_from(&**round, 0, Clock::get()?.unix_timestamp)?;
+        ctx.accounts.market_list.delist(&mut ctx.accounts.market_list_page, market.key())?;
+        release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+
+        emit!(MarketResolved {
+            market: market.key(),
+            winning_outcome,
+            transcript_hash: market.transcript_hash,
+            transcript_uri: market.transcript_uri.clone(),
+            claim_enabled_at: market.claim_enabled_at,
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(question: String, end_time: i64, market_id: u64, bump: u8)]
-pub struct InitializeMarket<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Market::LEN,
-        seeds = [b"market", authority.key().as_ref(), &market_id.to_le_bytes()],
-        bump
-    )]
-    pub market: Account<'info, Market>,
-    
-    #[account(
-        init,
-        payer = authority,
-        mint::decimals = 9,
-        mint::authority = market,
-    )]
-    pub yes_mint: Account<'info, Mint>,
-    
-    #[account(
-        init,
-        payer = authority,
-        mint::decimals = 9,
-        mint::authority = market,
-    )]
-    pub no_mint: Account<'info, Mint>,
-    
-    #[account(
-        init,
-        payer = authority,
-        token::mint = collateral_mint,
-        token::authority = market,
-    )]
-    pub collateral_vault: Account<'info, TokenAccount>,
-    
-    pub collateral_mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
-}
+    /// Resolve an `AgentProof` market on its linked agent's word. An operator
+    /// of the agent signs the outcome, backed by a proof the agent fulfilled
+    /// for this market after trading closed; that proof becomes the market's
+    /// transcript.
+    pub fn resolve_from_agent_proof(
+        ctx: Context<ResolveFromAgentProof>,
+        winning_outcome: Outcome,
+    ) -> Result<()> {
+        require!(!ctx.accounts.config.disable_resolve, ErrorCode::InstructionPaused);
+        require!(!ctx.accounts.agent.compromised, ErrorCode::AgentCompromised);
+        let market = &mut ctx.accounts.market;
+        let request = &ctx.accounts.proof_request;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(now >= market.end_time, ErrorCode::MarketNotEnded);
+        require!(!market.awaiting_reveal(now), ErrorCode::AwaitingReveal);
+        require!(request.requested_at >= market.end_time, ErrorCode::ProofPredatesClose);
+        require!(
+            request.proof_uri.len() <= MAX_TRANSCRIPT_URI,
+            ErrorCode::TranscriptUriTooLong
+        );
 
-#[derive(Accounts)]
-#[instruction(amount: u64, outcome: Outcome)]
-pub struct BuyTokens<'info> {
-    #[account(
-        mut,
-        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
-        bump = market.bump,
-    )]
-    pub market: Account<'info, Market>,
-    
-    #[account(mut)]
-    pub yes_mint: Account<'info, Mint>,
-    
-    #[account(mut)]
+        let source = AgentProof {
+            request,
+            outcome: winning_outcome,
+        };
+        market.resolve_from(&source, 0, now)?;
+        ctx.accounts.market_list.delist(&mut ctx.accounts.market_list_page, market.key())?;
+        release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+        market.transcript_hash = request.log_root;
+        market.transcript_uri = request.proof_uri.clone();
+
+        emit!(MarketResolved {
+            market: market.key(),
+            winning_outcome,
+            transcript_hash: market.transcript_hash,
+            transcript_uri: market.transcript_uri.clone(),
+            claim_enabled_at: market.claim_enabled_at,
+        });
+
+        Ok(())
+    }
+
+    /// The linked agent seals its verdict before the market closes, as
+    /// `outcome_commitment(outcome, salt)`. One-shot: it can't be changed
+    /// after seeing the book.
+    pub fn commit_outcome(ctx: Context<CommitOutcome>, commitment: [u8; 32]) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            Clock::get()?.unix_timestamp < market.end_time,
+            ErrorCode::MarketEnded
+        );
+        require!(market.outcome_commitment.is_none(), ErrorCode::CommitmentExists);
+        market.outcome_commitment = Some(commitment);
+
+        emit!(OutcomeCommitted {
+            market: market.key(),
+            agent: ctx.accounts.agent.key(),
+            commitment,
+        });
+
+        Ok(())
+    }
+
+    /// Resolve instantly by opening the agent's commitment. Permissionless,
+    /// since only the committed outcome can match; open until the reveal
+    /// window lapses, after which the market's normal resolution path
+    /// takes over.
+    pub fn reveal_outcome(ctx: Context<RevealOutcome>, outcome: Outcome, salt: [u8; 32]) -> Result<()> {
+        require!(!ctx.accounts.config.disable_resolve, ErrorCode::InstructionPaused);
+        let market = &mut ctx.accounts.market;
+        let now = Clock::get()?.unix_timestamp;
+        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(now >= market.end_time, ErrorCode::MarketNotEnded);
+        require!(market.awaiting_reveal(now), ErrorCode::NoCommitment);
+        require!(
+            market.outcome_commitment == Some(outcome_commitment(outcome, &salt)),
+            ErrorCode::CommitmentMismatch
+        );
+
+        market.settle(outcome, 0, now)?;
+        ctx.accounts.market_list.delist(&mut ctx.accounts.market_list_page, market.key())?;
+        release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+
+        emit!(MarketResolved {
+            market: market.key(),
+            winning_outcome: outcome,
+            transcript_hash: market.transcript_hash,
+            transcript_uri: market.transcript_uri.clone(),
+            claim_enabled_at: market.claim_enabled_at,
+        });
+
+        Ok(())
+    }
+
+    /// Fix the resolver set of a `Committee` market. One-shot, so the
+    /// authority can't swap resolvers once voting could have started.
+    pub fn configure_committee(
+        ctx: Context<ConfigureCommittee>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let market = &ctx.accounts.market;
+        require!(
+            market.resolution_mode == ResolutionMode::Committee,
+            ErrorCode::WrongResolutionMode
+        );
+        require!(
+            !members.is_empty()
+                && members.len() <= MAX_COMMITTEE
+                && threshold > 0
+                && threshold as usize <= members.len(),
+            ErrorCode::InvalidCommittee
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(!members[..i].contains(member), ErrorCode::InvalidCommittee);
+        }
+
+        let committee = &mut ctx.accounts.committee;
+        committee.market = market.key();
+        committee.members = members;
+        committee.threshold = threshold;
+        committee.yes_votes = 0;
+        committee.no_votes = 0;
+        committee.bump = ctx.bumps.committee;
+
+        Ok(())
+    }
+
+    /// A committee member's vote; members may change their vote until the
+    /// threshold is reached, at which point the market resolves.
+    pub fn vote_resolution(ctx: Context<VoteResolution>, outcome: Outcome) -> Result<()> {
+        require!(!ctx.accounts.config.disable_resolve, ErrorCode::InstructionPaused);
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(
+            Clock::get()?.unix_timestamp >= market.end_time,
+            ErrorCode::MarketNotEnded
+        );
+        require!(
+            !market.awaiting_reveal(Clock::get()?.unix_timestamp),
+            ErrorCode::AwaitingReveal
+        );
+
+        let committee = &mut ctx.accounts.committee;
+        let index = committee
+            .members
+            .iter()
+            .position(|member| *member == ctx.accounts.resolver.key())
+            .ok_or(ErrorCode::Unauthorized)?;
+        let bit = 1u8 << index;
+        let votes = match outcome {
+            Outcome::Yes => {
+                committee.no_votes &= !bit;
+                committee.yes_votes |= bit;
+                committee.yes_votes
+            }
+            Outcome::No => {
+                committee.yes_votes &= !bit;
+                committee.no_votes |= bit;
+                committee.no_votes
+            }
+        };
+
+        if votes.count_ones() >= committee.threshold as u32 {
+            market.settle(outcome, 0, Clock::get()?.unix_timestamp)?;
+            ctx.accounts.market_list.delist(&mut ctx.accounts.market_list_page, market.key())?;
+            release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+
+            emit!(MarketResolved {
+                market: market.key(),
+                winning_outcome: outcome,
+                transcript_hash: market.transcript_hash,
+                transcript_uri: market.transcript_uri.clone(),
+                claim_enabled_at: market.claim_enabled_at,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Publish a resolved market's outcome as a fixed-layout payload so
+    /// bridges and relayers can settle mirrored markets on other chains. The
+    /// payload is stored at `["resolution_message", market]` and emitted in
+    /// an event; posting is permissionless and happens once per market.
+    pub fn post_resolution_message(ctx: Context<PostResolutionMessage>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let winning_outcome = market.winning_side().ok_or(ErrorCode::MarketNotResolved)?;
+
+        let payload = resolution_payload(&market.key(), market, winning_outcome);
+        let message = &mut ctx.accounts.message;
+        message.market = market.key();
+        message.payload = payload;
+        message.posted_at = Clock::get()?.unix_timestamp;
+        message.bump = ctx.bumps.message;
+
+        emit!(ResolutionMessagePosted {
+            market: message.market,
+            message: message.key(),
+            payload: payload.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    /// Append a hashed note, optionally pointing at a URI, to the market's
+    /// resolution log at `["resolution_notes", market]`. Open to the market
+    /// authority and, on committee markets, any resolver. Notes are never
+    /// edited or removed, so the log is an audit trail of the resolution.
+    pub fn append_resolution_note(
+        ctx: Context<AppendResolutionNote>,
+        note_hash: [u8; 32],
+        uri: String,
+    ) -> Result<()> {
+        require!(uri.len() <= MAX_NOTE_URI, ErrorCode::NoteUriTooLong);
+        let author = ctx.accounts.author.key();
+        let is_resolver = ctx
+            .accounts
+            .committee
+            .as_ref()
+            .is_some_and(|committee| committee.members.contains(&author));
+        require!(
+            author == ctx.accounts.market.authority || is_resolver,
+            ErrorCode::Unauthorized
+        );
+
+        let notes = &mut ctx.accounts.notes;
+        require!(notes.notes.len() < MAX_RESOLUTION_NOTES, ErrorCode::TooManyNotes);
+
+        // Grow by one entry at a time so rent follows the log's length
+        let info = notes.to_account_info();
+        let new_len = ResolutionNotes::space(notes.notes.len() + 1);
+        let shortfall = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(info.lamports());
+        if shortfall > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.author.to_account_info(),
+                to: info.clone(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            system_program::transfer(cpi_ctx, shortfall)?;
+        }
+        info.resize(new_len)?;
+
+        let posted_at = Clock::get()?.unix_timestamp;
+        notes.market = ctx.accounts.market.key();
+        notes.bump = ctx.bumps.notes;
+        notes.notes.push(ResolutionNote {
+            author,
+            note_hash,
+            uri: uri.clone(),
+            posted_at,
+        });
+
+        emit!(ResolutionNoteAppended {
+            market: notes.market,
+            author,
+            index: (notes.notes.len() - 1) as u32,
+            note_hash,
+            uri,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the collateral paid out (borsh u64 return data). Winnings go
+    /// to the signer's own collateral account unless `payout_recipient`
+    /// names another owner, e.g. a cold wallet; the token owner still signs.
+    pub fn claim_winnings(
+        ctx: Context<ClaimWinnings>,
+        amount: u64,
+        payout_recipient: Option<Pubkey>,
+    ) -> Result<u64> {
+        let user = ctx.accounts.user.key();
+        let recipient = payout_recipient.unwrap_or(user);
+        require_keys_eq!(
+            ctx.accounts.user_collateral.owner,
+            recipient,
+            ErrorCode::PayoutRecipientMismatch
+        );
+        ctx.accounts.redeem(amount)?;
+
+        emit!(WinningsClaimed {
+            user,
+            recipient,
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// claim_winnings for partner payouts: the collateral transfer (USDC on
+    /// fiat-settled markets) carries `reference` as an SPL memo, and the
+    /// reference's hash is recorded in `ReferencedPayout` for reconciliation.
+    pub fn claim_with_reference(
+        ctx: Context<ClaimWithReference>,
+        amount: u64,
+        reference: String,
+    ) -> Result<u64> {
+        require!(
+            !reference.is_empty() && reference.len() <= MAX_PAYOUT_REFERENCE,
+            ErrorCode::InvalidPayoutReference
+        );
+        ctx.accounts.claim.redeem(amount)?;
+
+        let ix = Instruction {
+            program_id: MEMO_PROGRAM_ID,
+            accounts: vec![],
+            data: reference.as_bytes().to_vec(),
+        };
+        invoke(&ix, &[ctx.accounts.memo_program.to_account_info()])?;
+
+        emit!(ReferencedPayout {
+            market: ctx.accounts.claim.market.key(),
+            user: ctx.accounts.claim.user.key(),
+            user_collateral: ctx.accounts.claim.user_collateral.key(),
+            amount,
+            payout_reference: hash(reference.as_bytes()).to_bytes(),
+        });
+
+        Ok(amount)
+    }
+
+    /// Redeem the whole winning balance in several markets at once. Each
+    /// market takes `CLAIM_MANY_GROUP` remaining accounts: market, winning
+    /// mint, collateral vault, the user's winning token account and the
+    /// user's collateral account. Markets that can't pay out yet are skipped
+    /// instead of failing the batch; the returned list says what happened to
+    /// each, in order.
+    pub fn claim_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimMany<'info>>,
+    ) -> Result<Vec<BatchClaim>> {
+        require!(!ctx.accounts.config.disable_claim, ErrorCode::InstructionPaused);
+        let groups = ctx.remaining_accounts.chunks_exact(CLAIM_MANY_GROUP);
+        require!(
+            groups.remainder().is_empty() && (1..=MAX_CLAIM_MANY).contains(&groups.len()),
+            ErrorCode::InvalidClaimBatch
+        );
+        let user = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut results = Vec::with_capacity(groups.len());
+        for group in groups {
+            let market = Account::<Market>::try_from(&group[0])?;
+            let winning_mint = Account::<Mint>::try_from(&group[1])?;
+            let collateral_vault = Account::<TokenAccount>::try_from(&group[2])?;
+            let user_winning = Account::<TokenAccount>::try_from(&group[3])?;
+            let user_collateral = Account::<TokenAccount>::try_from(&group[4])?;
+            require_keys_eq!(
+                collateral_vault.key(),
+                market.collateral_vault,
+                ErrorCode::ReserveVaultMismatch
+            );
+            require_keys_eq!(
+                user_collateral.mint,
+                market.collateral_mint,
+                ErrorCode::InvalidClaimBatch
+            );
+
+            let status = match market.winning_side() {
+                None => BatchClaimStatus::NotResolved,
+                Some(_) if now < market.claim_enabled_at => BatchClaimStatus::ClaimsNotEnabled,
+                Some(outcome) => {
+                    let expected_mint = match outcome {
+                        Outcome::Yes => market.yes_mint,
+                        Outcome::No => market.no_mint,
+                    };
+                    require_keys_eq!(winning_mint.key(), expected_mint, ErrorCode::InvalidClaimBatch);
+                    require_keys_eq!(user_winning.mint, expected_mint, ErrorCode::InvalidClaimBatch);
+                    require_keys_eq!(user_winning.owner, user, ErrorCode::Unauthorized);
+                    if user_winning.amount == 0 {
+                        BatchClaimStatus::NothingToClaim
+                    } else {
+                        BatchClaimStatus::Claimed
+                    }
+                }
+            };
+            if status != BatchClaimStatus::Claimed {
+                results.push(BatchClaim { market: market.key(), status, amount: 0 });
+                continue;
+            }
+
+            let amount = user_winning.amount;
+            require!(collateral_vault.amount >= amount, ErrorCode::ReserveVaultMismatch);
+
+            let cpi_accounts = Burn {
+                mint: winning_mint.to_account_info(),
+                from: user_winning.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::burn(cpi_ctx, amount)?;
+
+            let seeds = &[
+                b"market".as_ref(),
+                market.authority.as_ref(),
+                &market.market_id.to_le_bytes(),
+                &[market.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: collateral_vault.to_account_info(),
+                to: user_collateral.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, amount)?;
+
+            emit!(WinningsClaimed {
+                user,
+                recipient: user_collateral.owner,
+                amount,
+            });
+            results.push(BatchClaim { market: market.key(), status, amount });
+        }
+
+        Ok(results)
+    }
+
+    /// Gasless claim: a relayer redeems `amount` winning tokens for a user who
+    /// signed `claim_for_message` off-chain. The signature is checked through
+    /// an Ed25519 program instruction placed right before this one. The user
+    /// must have delegated the tokens to the market PDA (e.g. an `approve`
+    /// bundled with their buy), since only an owner or delegate can burn.
+    pub fn claim_for(
+        ctx: Context<ClaimFor>,
+        amount: u64,
+        max_tip: u64,
+        expires_at: i64,
+        tip: u64,
+    ) -> Result<()> {
+        require!(!ctx.accounts.config.disable_claim, ErrorCode::InstructionPaused);
+        let market = &ctx.accounts.market;
+        let winning_outcome = market.winning_side().ok_or(ErrorCode::MarketNotResolved)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= market.claim_enabled_at, ErrorCode::ClaimsNotEnabled);
+        require!(now <= expires_at, ErrorCode::ClaimAuthorizationExpired);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            tip <= max_tip
+                && (tip as u128) * 10_000 <= (amount as u128) * MAX_RELAYER_TIP_BPS as u128,
+            ErrorCode::RelayerTipTooHigh
+        );
+        require!(
+            ctx.accounts.collateral_vault.amount >= amount,
+            ErrorCode::ReserveVaultMismatch
+        );
+
+        let expected_mint = match winning_outcome {
+            Outcome::Yes => market.yes_mint,
+            Outcome::No => market.no_mint,
+        };
+        require_keys_eq!(ctx.accounts.winning_mint.key(), expected_mint, ErrorCode::OutcomeMintMismatch);
+        require!(
+            ctx.accounts.user_winning_account.delegate == COption::Some(market.key()),
+            ErrorCode::ClaimNotDelegated
+        );
+
+        let user = ctx.accounts.user.key();
+        let message = claim_for_message(&market.key(), &user, amount, max_tip, expires_at);
+        let signature_ix = get_instruction_relative(-1, &ctx.accounts.instructions)
+            .map_err(|_| error!(ErrorCode::InvalidClaimSignature))?;
+        require!(
+            is_ed25519_signature(&signature_ix, &user, &message),
+            ErrorCode::InvalidClaimSignature
+        );
+
+        let authorization = &mut ctx.accounts.authorization;
+        authorization.user = user;
+        authorization.market = market.key();
+        authorization.bump = ctx.bumps.authorization;
+
+        let seeds = &[
+            b"market".as_ref(),
+            market.authority.as_ref(),
+            &market.market_id.to_le_bytes(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Market PDA burns as the user's delegate
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.winning_mint.to_account_info(),
+            from: ctx.accounts.user_winning_account.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::burn(cpi_ctx, amount)?;
+
+        for (to, payout) in [
+            (&ctx.accounts.user_collateral, amount - tip),
+            (&ctx.accounts.relayer_collateral, tip),
+        ] {
+            if payout == 0 {
+                continue;
+            }
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, payout)?;
+        }
+
+        emit!(WinningsClaimed {
+            user,
+            recipient: user,
+            amount,
+        });
+        emit!(ClaimRelayed {
+            market: market.key(),
+            user,
+            relayer: ctx.accounts.relayer.key(),
+            tip,
+        });
+
+        Ok(())
+    }
+
+    /// Outcome tokens minted for `amount` collateral. Read-only; meant for
+    /// simulateTransaction, the value comes back as return data.
+    pub fn quote_buy(ctx: Context<QuoteMarket>, amount: u64) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(
+            Clock::get()?.unix_timestamp >= market.opens_at,
+            ErrorCode::MarketNotOpen
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market.end_time,
+            ErrorCode::MarketEnded
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount >= market.min_trade_amount, ErrorCode::TradeTooSmall);
+
+        Ok(amount)
+    }
+
+    /// Collateral returned for selling `amount` outcome tokens. Read-only.
+    pub fn quote_sell(ctx: Context<QuoteMarket>, amount: u64) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(
+            Clock::get()?.unix_timestamp >= market.opens_at,
+            ErrorCode::MarketNotOpen
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market.end_time,
+            ErrorCode::MarketEnded
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount >= market.min_trade_amount, ErrorCode::TradeTooSmall);
+        require!(
+            ctx.accounts.collateral_vault.amount >= amount,
+            ErrorCode::ReserveVaultMismatch
+        );
+
+        Ok(amount)
+    }
+
+    /// Collateral paid for redeeming `amount` tokens of `outcome`; zero for
+    /// the losing side. Read-only.
+    pub fn quote_claim(ctx: Context<QuoteMarket>, amount: u64, outcome: Outcome) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        require!(market.is_resolved, ErrorCode::MarketNotResolved);
+        require!(
+            Clock::get()?.unix_timestamp >= market.claim_enabled_at,
+            ErrorCode::ClaimsNotEnabled
+        );
+        if market.winning_side() != Some(outcome) {
+            return Ok(0);
+        }
+        require!(
+            ctx.accounts.collateral_vault.amount >= amount,
+            ErrorCode::ReserveVaultMismatch
+        );
+
+        Ok(amount)
+    }
+
+    /// Compare what the vault owes against what it holds. Before resolution
+    /// every outcome token can be sold back 1:1; afterwards only the winning
+    /// side redeems. Read-only: returns the report and emits it as an event.
+    pub fn solvency_report(ctx: Context<SolvencyCheck>) -> Result<SolvencyReport> {
+        let market = &ctx.accounts.market;
+        let yes_supply = ctx.accounts.yes_mint.supply;
+        let no_supply = ctx.accounts.no_mint.supply;
+        let liabilities = match market.winning_side() {
+            Some(Outcome::Yes) => yes_supply,
+            Some(Outcome::No) => no_supply,
+            None => yes_supply.checked_add(no_supply).ok_or(ErrorCode::Overflow)?,
+        };
+        let vault_balance = ctx.accounts.collateral_vault.amount;
+
+        let report = SolvencyReport {
+            market: market.key(),
+            liabilities,
+            vault_balance,
+            surplus: vault_balance as i128 - liabilities as i128,
+        };
+        emit!(report.clone());
+
+        Ok(report)
+    }
+
+    /// What this deployment is: crate version, `FEATURE_*` bits and the
+    /// config PDA. Clients should gate on these rather than probe.
+    pub fn program_info(_ctx: Context<GetProgramInfo>) -> Result<ProgramInfo> {
+        Ok(ProgramInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            features: FEATURE_FLAGS,
+            config: Pubkey::find_program_address(&[b"config"], &crate::ID).0,
+        })
+    }
+
+    /// The market authority seeds the vault with collateral it takes no
+    /// tokens for. It is tracked as creator liquidity, the only part of the
+    /// vault withdraw_surplus can return.
+    pub fn add_liquidity(ctx: Context<AddLiquidity>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.authority_collateral.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        market.creator_liquidity = market
+            .creator_liquidity
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(LiquidityAdded {
+            market: market.key(),
+            amount,
+            creator_liquidity: market.creator_liquidity,
+        });
+
+        Ok(())
+    }
+
+    /// After resolution, return the unused part of the creator's liquidity:
+    /// whatever of `creator_liquidity` the vault holds beyond the winning
+    /// supply. Losing traders' collateral is never paid to the authority, so
+    /// resolving toward the thinner side earns it nothing; winners stay fully
+    /// covered.
+    pub fn withdraw_surplus(ctx: Context<WithdrawSurplus>) -> Result<u64> {
+        let market = &ctx.accounts.market;
+        let winning_outcome = market.winning_side().ok_or(ErrorCode::MarketNotResolved)?;
+        require!(
+            Clock::get()?.unix_timestamp >= market.claim_enabled_at,
+            ErrorCode::ClaimsNotEnabled
+        );
+
+        let liabilities = match winning_outcome {
+            Outcome::Yes => ctx.accounts.yes_mint.supply,
+            Outcome::No => ctx.accounts.no_mint.supply,
+        };
+        let surplus = ctx
+            .accounts
+            .collateral_vault
+            .amount
+            .saturating_sub(liabilities)
+            .min(market.creator_liquidity);
+        require!(surplus > 0, ErrorCode::NoSurplus);
+
+        let seeds = &[
+            b"market".as_ref(),
+            market.authority.as_ref(),
+            &market.market_id.to_le_bytes(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.collateral_vault.to_account_info(),
+            to: ctx.accounts.authority_collateral.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, surplus)?;
+        let market = &mut ctx.accounts.market;
+        market.creator_liquidity -= surplus;
+
+        emit!(SurplusWithdrawn {
+            market: market.key(),
+            amount: surplus,
+            remaining_liabilities: liabilities,
+        });
+
+        Ok(surplus)
+    }
+
+    /// Realign the tracked supplies with the outcome mints (claims burn tokens
+    /// without touching the counters). Permissionless: it only copies on-chain state.
+    pub fn reconcile_supply(ctx: Context<ReconcileSupply>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        market.total_yes_supply = ctx.accounts.yes_mint.supply;
+        market.total_no_supply = ctx.accounts.no_mint.supply;
+
+        emit!(SupplyReconciled {
+            market: market.key(),
+            total_yes_supply: market.total_yes_supply,
+            total_no_supply: market.total_no_supply,
+            vault_balance: ctx.accounts.collateral_vault.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Emit a MarketStateCheckpoint now rather than waiting for the next
+    /// CHECKPOINT_INTERVAL trades. Permissionless: it only reports state.
+    pub fn checkpoint_market(ctx: Context<CheckpointMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        emit!(market.checkpoint(
+            market.key(),
+            ctx.accounts.collateral_vault.amount,
+            Clock::get()?.slot,
+        ));
+        Ok(())
+    }
+
+    /// Permissionless cleanup of a dead market: once it ended GC_GRACE_SECS
+    /// ago without a single trade or faucet mint, any collateral left in the
+    /// vault (e.g. added liquidity) goes back to the creator, the market and
+    /// its question index are closed to the creator, and the caller keeps the
+    /// vault's rent as a tip. Frees the question for a new market.
+    pub fn gc_market(ctx: Context<GcMarket>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let collectable_at = market
+            .end_time
+            .checked_add(GC_GRACE_SECS)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            Clock::get()?.unix_timestamp >= collectable_at,
+            ErrorCode::MarketNotStale
+        );
+        // Zero supply isn't enough: a market that traded has a history worth keeping
+        require!(market.volume == 0, ErrorCode::MarketHasVolume);
+
+        let seeds = &[
+            b"market".as_ref(),
+            market.authority.as_ref(),
+            &market.market_id.to_le_bytes(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let refund = ctx.accounts.collateral_vault.amount;
+        if refund > 0 {
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.collateral_vault.to_account_info(),
+                to: ctx.accounts.authority_collateral.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::transfer(cpi_ctx, refund)?;
+        }
+
+        let tip = ctx.accounts.collateral_vault.to_account_info().lamports();
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.collateral_vault.to_account_info(),
+            destination: ctx.accounts.caller.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        // Already gone if the market was resolved
+        ctx.accounts
+            .market_list
+            .delist(&mut ctx.accounts.market_list_page, market.key())?;
+        if !market.is_resolved {
+            release_agent_slot(market, ctx.accounts.agent_markets.as_deref_mut())?;
+        }
+
+        emit!(MarketCollected {
+            market: market.key(),
+            caller: ctx.accounts.caller.key(),
+            refund,
+            tip,
+        });
+
+        Ok(())
+    }
+
+    /// Demo onboarding: the market authority sponsors `amount` YES and NO
+    /// tokens for a user and tops their SOL up to FAUCET_LAMPORTS. The sponsor
+    /// deposits the backing collateral, so the vault stays fully funded.
+    /// Not compiled into mainnet builds.
+    #[cfg(not(feature = "mainnet"))]
+    pub fn faucet(ctx: Context<Faucet>, amount: u64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        require!(!market.frozen, ErrorCode::MarketFrozen);
+        require!(
+            amount > 0 && amount <= FAUCET_MAX_AMOUNT,
+            ErrorCode::InvalidAmount
+        );
+
+        // Either side can be sold back for collateral, so back both legs
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.sponsor_collateral.to_account_info(),
+            to: ctx.accounts.collateral_vault.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount.checked_mul(2).ok_or(ErrorCode::Overflow)?)?;
+
+        let market_authority = market.authority;
+        let market_id = market.market_id.to_le_bytes();
+        let seeds = &[
+            b"market".as_ref(),
+            market_authority.as_ref(),
+            &market_id,
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        for (mint, to) in [
+            (&ctx.accounts.yes_mint, &ctx.accounts.user_yes_account),
+            (&ctx.accounts.no_mint, &ctx.accounts.user_no_account),
+        ] {
+            let cpi_accounts = MintTo {
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: market.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            token::mint_to(cpi_ctx, amount)?;
+        }
+        market.total_yes_supply = market
+            .total_yes_supply
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        market.total_no_supply = market
+            .total_no_supply
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        // Sponsored tokens are live positions; the market can no longer be collected
+        market.volume = market
+            .volume
+            .checked_add(amount.checked_mul(2).ok_or(ErrorCode::Overflow)?)
+            .ok_or(ErrorCode::Overflow)?;
+        // Only one leg can win, so half of the backing ends up unused
+        market.creator_liquidity = market
+            .creator_liquidity
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let balance = ctx.accounts.user.lamports();
+        if balance < FAUCET_LAMPORTS {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.user.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, FAUCET_LAMPORTS - balance)?;
+        }
+
+        Ok(())
+    }
+
+    /// Escrow a large position behind a one-of-one receipt token. Whoever
+    /// holds the receipt can unwrap it, so the whole position trades OTC as
+    /// a single NFT-like token.
+    pub fn wrap_position(ctx: Context<WrapPosition>, outcome: Outcome, amount: u64) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let expected_mint = match outcome {
+            Outcome::Yes => market.yes_mint,
+            Outcome::No => market.no_mint,
+        };
+        require_keys_eq!(ctx.accounts.outcome_mint.key(), expected_mint, ErrorCode::OutcomeMintMismatch);
+        require!(amount >= POSITION_RECEIPT_MIN, ErrorCode::PositionTooSmall);
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_outcome_account.to_account_info(),
+            to: ctx.accounts.escrow.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+
+        let receipt_mint = ctx.accounts.receipt_mint.key();
+        let position = &mut ctx.accounts.position;
+        position.market = market.key();
+        position.receipt_mint = receipt_mint;
+        position.outcome = outcome;
+        position.size = amount;
+        position.opened_at = Clock::get()?.unix_timestamp;
+        position.bump = ctx.bumps.position;
+
+        let seeds = &[b"position".as_ref(), receipt_mint.as_ref(), &[position.bump]];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.receipt_mint.to_account_info(),
+            to: ctx.accounts.user_receipt_account.to_account_info(),
+            authority: position.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::mint_to(cpi_ctx, 1)?;
+
+        emit!(PositionWrapped {
+            market: position.market,
+            position: position.key(),
+            receipt_mint,
+            owner: ctx.accounts.user.key(),
+            outcome,
+            size: amount,
+        });
+
+        Ok(())
+    }
+
+    /// Burn a position receipt and release the escrowed outcome tokens to
+    /// the receipt's current holder, who can then sell or claim them.
+    pub fn unwrap_position(ctx: Context<UnwrapPosition>) -> Result<()> {
+        let cpi_accounts = Burn {
+            mint: ctx.accounts.receipt_mint.to_account_info(),
+            from: ctx.accounts.holder_receipt_account.to_account_info(),
+            authority: ctx.accounts.holder.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::burn(cpi_ctx, 1)?;
+
+        let position = &ctx.accounts.position;
+        let receipt_mint = position.receipt_mint;
+        let seeds = &[b"position".as_ref(), receipt_mint.as_ref(), &[position.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.escrow.to_account_info(),
+            to: ctx.accounts.holder_outcome_account.to_account_info(),
+            authority: position.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, position.size)?;
+
+        let cpi_accounts = CloseAccount {
+            account: ctx.accounts.escrow.to_account_info(),
+            destination: ctx.accounts.holder.to_account_info(),
+            authority: position.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::close_account(cpi_ctx)?;
+
+        emit!(PositionUnwrapped {
+            market: position.market,
+            position: position.key(),
+            holder: ctx.accounts.holder.key(),
+            outcome: position.outcome,
+            size: position.size,
+        });
+
+        Ok(())
+    }
+
+    /// Halt an open market whose linked agent the registry flags as
+    /// compromised. Permissionless, so every affected market can be frozen
+    /// as soon as the flag is raised.
+    pub fn freeze_agent_market(ctx: Context<FreezeAgentMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        market.frozen = true;
+
+        emit!(MarketFrozen {
+            market: market.key(),
+            agent: ctx.accounts.agent.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Halt trading and resolution on the authority's or pauser's call
+    pub fn pause_market(ctx: Context<PauseMarket>) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketAlreadyResolved);
+        market.frozen = true;
+
+        emit!(MarketPaused {
+            market: market.key(),
+            pauser: ctx.accounts.pauser.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Lift a freeze once the market authority (or its pauser) has dealt
+    /// with the incident
+    pub fn unfreeze_market(ctx: Context<UnfreezeMarket>) -> Result<()> {
+        ctx.accounts.market.frozen = false;
+        Ok(())
+    }
+
+    /// Delegate single powers without handing over the market: a resolver
+    /// may call resolve_market and a pauser may pause and unfreeze. The
+    /// authority keeps both powers; `None` revokes a role.
+    pub fn set_market_roles(
+        ctx: Context<SetMarketRoles>,
+        resolver: Option<Pubkey>,
+        pauser: Option<Pubkey>,
+    ) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.market = ctx.accounts.market.key();
+        roles.resolver = resolver;
+        roles.pauser = pauser;
+        roles.bump = ctx.bumps.roles;
+
+        emit!(MarketRolesSet {
+            market: roles.market,
+            resolver,
+            pauser,
+        });
+
+        Ok(())
+    }
+
+    /// Turn same-slot round-trip blocking on or off. While on, traders need a
+    /// TraderState (open_trader_state) for buy/sell/mm_rebalance.
+    pub fn set_round_trip_guard(ctx: Context<SetRoundTripGuard>, enabled: bool) -> Result<()> {
+        ctx.accounts.market.block_round_trips = enabled;
+        Ok(())
+    }
+
+    pub fn open_trader_state(ctx: Context<OpenTraderState>) -> Result<()> {
+        let trader_state = &mut ctx.accounts.trader_state;
+        trader_state.market = ctx.accounts.market.key();
+        trader_state.trader = ctx.accounts.trader.key();
+        trader_state.last_buy_slot = [0; 2];
+        trader_state.last_sell_slot = [0; 2];
+        trader_state.bump = ctx.bumps.trader_state;
+        Ok(())
+    }
+
+    /// Create the agent's address lookup table, with its AgentMarkets PDA as
+    /// the table authority so it can only be extended through this program.
+    /// Signed by the agent's registry authority.
+    pub fn create_agent_lookup_table(ctx: Context<CreateAgentLookupTable>, recent_slot: u64) -> Result<()> {
+        let agent_markets = &mut ctx.accounts.agent_markets;
+        require!(agent_markets.lookup_table.is_none(), ErrorCode::LookupTableExists);
+
+        let (ix, lookup_table) = alt_instruction::create_lookup_table(
+            agent_markets.key(),
+            ctx.accounts.authority.key(),
+            recent_slot,
+        );
+        require_keys_eq!(ctx.accounts.lookup_table.key(), lookup_table, ErrorCode::LookupTableMismatch);
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                agent_markets.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.address_lookup_table_program.to_account_info(),
+            ],
+        )?;
+        agent_markets.lookup_table = Some(lookup_table);
+
+        Ok(())
+    }
+
+    /// Append one of the agent's unresolved markets (market, mints, vault) to
+    /// its lookup table. The market authority signs and pays for the table's
+    /// growth; a market already in the table is skipped, so repeats can't
+    /// fill it up.
+    pub fn extend_agent_lookup_table(ctx: Context<ExtendAgentLookupTable>) -> Result<()> {
+        let agent_markets = &ctx.accounts.agent_markets;
+        let market = &mut ctx.accounts.market;
+        require!(!market.is_resolved, ErrorCode::MarketResolved);
+        if market.in_lookup_table {
+            return Ok(());
+        }
+
+        let ix = alt_instruction::extend_lookup_table(
+            ctx.accounts.lookup_table.key(),
+            agent_markets.key(),
+            Some(ctx.accounts.authority.key()),
+            vec![market.key(), market.yes_mint, market.no_mint, market.collateral_vault],
+        );
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                agent_markets.to_account_info(),
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.address_lookup_table_program.to_account_info(),
+            ],
+            &[&[b"agent_markets", agent_markets.agent.as_ref(), &[agent_markets.bump]]],
+        )?;
+        market.in_lookup_table = true;
+
+        Ok(())
+    }
+
+    /// Reject buys and sells below `min_trade_amount` (collateral base units)
+    /// to keep dust trades out of the event history.
+    pub fn set_min_trade(ctx: Context<SetMinTrade>, min_trade_amount: u64) -> Result<()> {
+        ctx.accounts.market.min_trade_amount = min_trade_amount;
+        Ok(())
+    }
+
+    /// Restrict trading to a merkle set of wallets (see `allowlist_leaf`), or
+    /// reopen the market to everyone with None.
+    pub fn set_allowlist(ctx: Context<SetAllowlist>, allowlist_root: Option<[u8; 32]>) -> Result<()> {
+        ctx.accounts.market.allowlist_root = allowlist_root;
+        Ok(())
+    }
+
+    /// Prove membership in the market's allowlist once; the resulting pass
+    /// is what buy/sell check.
+    pub fn join_allowlist(ctx: Context<JoinAllowlist>, proof: Vec<[u8; 32]>) -> Result<()> {
+        require!(proof.len() <= MAX_MERKLE_PROOF, ErrorCode::InvalidMerkleProof);
+        let root = ctx.accounts.market.allowlist_root.ok_or(ErrorCode::NoAllowlist)?;
+        let trader = ctx.accounts.trader.key();
+        require!(
+            verify_merkle_proof(&proof, root, allowlist_leaf(&trader)),
+            ErrorCode::InvalidMerkleProof
+        );
+
+        let pass = &mut ctx.accounts.allowlist_pass;
+        pass.market = ctx.accounts.market.key();
+        pass.trader = trader;
+        pass.root = root;
+        pass.bump = ctx.bumps.allowlist_pass;
+
+        Ok(())
+    }
+
+    /// Schedule when trading opens, so a round can be advertised ahead of
+    /// time. Only allowed before anyone holds a position.
+    pub fn set_opens_at(ctx: Context<SetOpensAt>, opens_at: i64) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        require!(
+            market.total_yes_supply == 0 && market.total_no_supply == 0,
+            ErrorCode::MarketAlreadyTraded
+        );
+        require!(opens_at < market.end_time, ErrorCode::InvalidEndTime);
+        market.opens_at = opens_at;
+        Ok(())
+    }
+
+    /// Add or replace the market's question in one language (BCP 47 code, e.g. "pt-BR")
+    pub fn set_translation(
+        ctx: Context<SetTranslation>,
+        lang_code: String,
+        statement: String,
+    ) -> Result<()> {
+        require!(
+            !lang_code.is_empty() && lang_code.len() <= MAX_LANG_CODE,
+            ErrorCode::InvalidLangCode
+        );
+        require!(statement.len() <= MAX_QUESTION, ErrorCode::QuestionTooLong);
+        require!(is_valid_statement(&statement), ErrorCode::InvalidStatement);
+
+        let translations = &mut ctx.accounts.translations;
+        translations.market = ctx.accounts.market.key();
+        translations.bump = ctx.bumps.translations;
+
+        match translations.entries.iter_mut().find(|t| t.lang_code == lang_code) {
+            Some(entry) => entry.statement = statement,
+            None => {
+                require!(
+                    translations.entries.len() < MAX_TRANSLATIONS,
+                    ErrorCode::TooManyTranslations
+                );
+                translations.entries.push(Translation { lang_code, statement });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_translation(ctx: Context<RemoveTranslation>, lang_code: String) -> Result<()> {
+        let entries = &mut ctx.accounts.translations.entries;
+        let position = entries
+            .iter()
+            .position(|t| t.lang_code == lang_code)
+            .ok_or(ErrorCode::TranslationNotFound)?;
+        entries.remove(position);
+
+        Ok(())
+    }
+
+    /// Fund a bonus distribution for a resolved market. `merkle_root` commits
+    /// to `(index, claimant, amount)` leaves snapshotted off-chain (see
+    /// `airdrop_leaf`); the sponsor deposits `total_amount` of `reward_mint`.
+    pub fn create_airdrop(
+        ctx: Context<CreateAirdrop>,
+        airdrop_id: u64,
+        merkle_root: [u8; 32],
+        total_amount: u64,
+    ) -> Result<()> {
+        require!(ctx.accounts.market.is_resolved, ErrorCode::MarketNotResolved);
+        require!(total_amount > 0, ErrorCode::InvalidAmount);
+
+        let airdrop = &mut ctx.accounts.airdrop;
+        airdrop.market = ctx.accounts.market.key();
+        airdrop.airdrop_id = airdrop_id;
+        airdrop.sponsor = ctx.accounts.sponsor.key();
+        airdrop.reward_mint = ctx.accounts.reward_mint.key();
+        airdrop.reward_vault = ctx.accounts.reward_vault.key();
+        airdrop.merkle_root = merkle_root;
+        airdrop.total_amount = total_amount;
+        airdrop.claimed_amount = 0;
+        airdrop.bump = ctx.bumps.airdrop;
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.sponsor_tokens.to_account_info(),
+            to: ctx.accounts.reward_vault.to_account_info(),
+            authority: ctx.accounts.sponsor.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, total_amount)?;
+
+        emit!(AirdropCreated {
+            market: airdrop.market,
+            airdrop: airdrop.key(),
+            reward_mint: airdrop.reward_mint,
+            merkle_root,
+            total_amount,
+        });
+
+        Ok(())
+    }
+
+    /// One-time setup of the protocol config. Only the program's upgrade
+    /// authority may call it, so nobody can front-run the deploy and take
+    /// the admin seat; it becomes the first admin.
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.disable_create = false;
+        config.disable_trade = false;
+        config.disable_resolve = false;
+        config.disable_claim = false;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Hand the config over to a new admin, e.g. a multisig.
+    pub fn set_admin(ctx: Context<SetAdmin>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let previous = config.admin;
+        config.admin = new_admin;
+
+        emit!(AdminChanged {
+            previous,
+            admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Incident switches. Pausing trade only stops buys, so holders can
+    /// still sell out; per-market halts go through `frozen` instead.
+    pub fn set_pause_flags(
+        ctx: Context<SetPauseFlags>,
+        disable_create: bool,
+        disable_trade: bool,
+        disable_resolve: bool,
+        disable_claim: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.disable_create = disable_create;
+        config.disable_trade = disable_trade;
+        config.disable_resolve = disable_resolve;
+        config.disable_claim = disable_claim;
+
+        emit!(PauseFlagsUpdated {
+            disable_create,
+            disable_trade,
+            disable_resolve,
+            disable_claim,
+        });
+
+        Ok(())
+    }
+
+    /// Claim one leaf of an airdrop. The receipt PDA is `init`, so each leaf
+    /// index can be claimed once.
+    pub fn claim_airdrop(
+        ctx: Context<ClaimAirdrop>,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(proof.len() <= MAX_MERKLE_PROOF, ErrorCode::InvalidMerkleProof);
+        let airdrop = &mut ctx.accounts.airdrop;
+        let claimant = ctx.accounts.claimant.key();
+
+        let leaf = airdrop_leaf(index, &claimant, amount);
+        require!(
+            verify_merkle_proof(&proof, airdrop.merkle_root, leaf),
+            ErrorCode::InvalidMerkleProof
+        );
+
+        let claimed_amount = airdrop
+            .claimed_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(claimed_amount <= airdrop.total_amount, ErrorCode::AirdropExhausted);
+        airdrop.claimed_amount = claimed_amount;
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.airdrop = airdrop.key();
+        receipt.index = index;
+        receipt.claimant = claimant;
+        receipt.bump = ctx.bumps.receipt;
+
+        let market_key = airdrop.market;
+        let airdrop_id = airdrop.airdrop_id.to_le_bytes();
+        let seeds = &[
+            b"airdrop".as_ref(),
+            market_key.as_ref(),
+            &airdrop_id,
+            &[airdrop.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reward_vault.to_account_info(),
+            to: ctx.accounts.claimant_tokens.to_account_info(),
+            authority: airdrop.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        emit!(AirdropClaimed {
+            airdrop: airdrop.key(),
+            claimant,
+            index,
+            amount,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(question: String, end_time: i64, market_id: u64, bump: u8)]
+pub struct InitializeMarket<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Market::INIT_SPACE,
+        seeds = [b"market", authority.key().as_ref(), &market_id.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// Reverse lookup from the question to this market; `init` rejects duplicates
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MarketIndex::INIT_SPACE,
+        seeds = [b"market_index", question_hash(&question).as_ref()],
+        bump
+    )]
+    pub market_index: Account<'info, MarketIndex>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MarketList::INIT_SPACE,
+        seeds = [b"market_list"],
+        bump
+    )]
+    pub market_list: Account<'info, MarketList>,
+    
+    /// Always `market_list.current_page()`
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MarketListPage::INIT_SPACE,
+        seeds = [b"market_list".as_ref(), &market_list.current_page().to_le_bytes()],
+        bump
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Quote asset the market is denominated in (e.g. the agent's fan token)
+    pub collateral_mint: Account<'info, Mint>,
+    
+    // Outcome tokens redeem 1:1 for collateral, so they share its decimals
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+    )]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+    )]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = collateral_mint,
+        token::authority = market,
+    )]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> InitializeMarket<'info> {
+    fn init_market(
+        &mut self,
+        bumps: &InitializeMarketBumps,
+        question: String,
+        end_time: i64,
+        market_id: u64,
+        bump: u8,
+        resolution_mode: ResolutionMode,
+    ) -> Result<()> {
+        require!(!self.config.disable_create, ErrorCode::InstructionPaused);
+        let market = &mut self.market;
+        
+        require!(question.len() <= MAX_QUESTION, ErrorCode::QuestionTooLong);
+        require!(is_valid_statement(&question), ErrorCode::InvalidStatement);
+        require!(end_time > Clock::get()?.unix_timestamp, ErrorCode::InvalidEndTime);
+
+        market.authority = self.authority.key();
+        market.market_id = market_id;
+        market.agent = None;
+        market.question = question;
+        market.yes_mint = self.yes_mint.key();
+        market.no_mint = self.no_mint.key();
+        market.collateral_mint = self.collateral_mint.key();
+        market.collateral_vault = self.collateral_vault.key();
+        market.allowlist_root = None;
+        market.opens_at = 0;
+        market.end_time = end_time;
+        market.resolution_mode = resolution_mode;
+        market.is_resolved = false;
+        market.winning_outcome = None;
+        market.claim_enabled_at = 0;
+        market.transcript_hash = [0u8; 32];
+        market.transcript_uri = String::new();
+        market.total_yes_supply = 0;
+        market.total_no_supply = 0;
+        market.block_round_trips = false;
+        market.frozen = false;
+        market.min_trade_amount = 0;
+        market.outcome_commitment = None;
+        market.list_page = self.market_list.current_page();
+        market.trade_count = 0;
+        market.volume = 0;
+        market.in_lookup_table = false;
+        market.creator_liquidity = 0;
+        market.bump = bump;
+
+        let market_index = &mut self.market_index;
+        market_index.market = market.key();
+        market_index.bump = bumps.market_index;
+
+        // Append to the global list of unresolved markets
+        let market_list = &mut self.market_list;
+        let list_page = &mut self.market_list_page;
+        if market_list.listed == 0 {
+            market_list.bump = bumps.market_list;
+        }
+        if list_page.markets.is_empty() {
+            list_page.page = market_list.current_page();
+            list_page.bump = bumps.market_list_page;
+        }
+        list_page.markets.push(market.key());
+        market_list.listed = market_list.listed.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        market_list.active = market_list.active.checked_add(1).ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateMarketFromRequest<'info> {
+    pub create: InitializeMarket<'info>,
+    
+    pub proof_request: Account<'info, ProofRequest>,
+    
+    /// Linked markets count against the agent's tier cap, so only the agent's
+    /// authority or one of its operators may create them
+    #[account(
+        address = proof_request.agent @ ErrorCode::AgentMismatch,
+        constraint = create.authority.key() == agent.authority
+            || agent.is_operator(&create.authority.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub agent: Account<'info, Agent>,
+    
+    /// Registry config holding the tier limits
+    pub registry: Account<'info, Registry>,
+    
+    #[account(
+        init_if_needed,
+        payer = create.authority,
+        space = 8 + AgentMarkets::INIT_SPACE,
+        seeds = [b"agent_markets", proof_request.agent.as_ref()],
+        bump
+    )]
+    pub agent_markets: Account<'info, AgentMarkets>,
+    
+    /// Page the new market is appended to; always `agent_markets.current_page()`
+    #[account(
+        init_if_needed,
+        payer = create.authority,
+        space = 8 + AgentMarketsPage::INIT_SPACE,
+        seeds = [b"agent_markets", proof_request.agent.as_ref(), &agent_markets.current_page().to_le_bytes()],
+        bump
+    )]
+    pub agent_markets_page: Account<'info, AgentMarketsPage>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloneMarket<'info> {
+    pub create: InitializeMarket<'info>,
+    
+    #[account(
+        constraint = source_market.authority == create.authority.key() @ ErrorCode::Unauthorized,
+        constraint = source_market.collateral_mint == create.collateral_mint.key() @ ErrorCode::CollateralMismatch,
+    )]
+    pub source_market: Account<'info, Market>,
+    
+    /// The source's linked agent and the registry, for its tier's market
+    /// cap; like the index accounts below, required when it has one
+    #[account(constraint = source_market.agent == Some(agent.key()) @ ErrorCode::AgentMismatch)]
+    pub agent: Option<Account<'info, Agent>>,
+    
+    pub registry: Option<Account<'info, Registry>>,
+    
+    /// Index of the source's linked agent
+    #[account(
+        init_if_needed,
+        payer = create.authority,
+        space = 8 + AgentMarkets::INIT_SPACE,
+        seeds = [b"agent_markets", source_market.agent.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+    
+    #[account(
+        init_if_needed,
+        payer = create.authority,
+        space = 8 + AgentMarketsPage::INIT_SPACE,
+        seeds = [
+            b"agent_markets".as_ref(),
+            source_market.agent.unwrap_or_default().as_ref(),
+            &agent_markets.as_ref().map_or(0, |index| index.current_page()).to_le_bytes(),
+        ],
+        bump
+    )]
+    pub agent_markets_page: Option<Account<'info, AgentMarketsPage>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct CreateTemplate<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Template::INIT_SPACE,
+        seeds = [b"template", authority.key().as_ref(), &template_id.to_le_bytes()],
+        bump
+    )]
+    pub template: Account<'info, Template>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateFromTemplate<'info> {
+    pub create: InitializeMarket<'info>,
+    
+    #[account(
+        seeds = [b"template", template.authority.as_ref(), &template.template_id.to_le_bytes()],
+        bump = template.bump,
+    )]
+    pub template: Account<'info, Template>,
+}
+
+#[derive(Accounts)]
+#[instruction(question: String, end_time: i64, battle_id: u64)]
+pub struct ScheduleBattle<'info> {
+    pub create: InitializeMarket<'info>,
+    
+    #[account(
+        init,
+        payer = create.authority,
+        space = 8 + Battle::INIT_SPACE,
+        seeds = [b"battle", create.authority.key().as_ref(), &battle_id.to_le_bytes()],
+        bump
+    )]
+    pub battle: Account<'info, Battle>,
+    
+    pub agent_a: Account<'info, Agent>,
+    
+    pub agent_b: Account<'info, Agent>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, outcome: Outcome)]
+pub struct BuyTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub user_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.yes_mint)]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.no_mint)]
+    pub user_no_account: Account<'info, TokenAccount>,
+    
+    /// Required while the market has an allowlist
+    #[account(
+        seeds = [b"allowlist_pass", market.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_pass.bump,
+    )]
+    pub allowlist_pass: Option<Account<'info, AllowlistPass>>,
+    
+    /// Required while the market blocks same-slot round trips
+    #[account(
+        mut,
+        seeds = [b"trader_state", market.key().as_ref(), user.key().as_ref()],
+        bump = trader_state.bump,
+    )]
+    pub trader_state: Option<Account<'info, TraderState>>,
+    
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, outcome: Outcome)]
+pub struct SellTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub user_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.yes_mint)]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.no_mint)]
+    pub user_no_account: Account<'info, TokenAccount>,
+    
+    /// Required while the market has an allowlist
+    #[account(
+        seeds = [b"allowlist_pass", market.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_pass.bump,
+    )]
+    pub allowlist_pass: Option<Account<'info, AllowlistPass>>,
+    
+    /// Required while the market blocks same-slot round trips
+    #[account(
+        mut,
+        seeds = [b"trader_state", market.key().as_ref(), user.key().as_ref()],
+        bump = trader_state.bump,
+    )]
+    pub trader_state: Option<Account<'info, TraderState>>,
+    
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MmRebalance<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub user_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.yes_mint)]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.no_mint)]
+    pub user_no_account: Account<'info, TokenAccount>,
+    
+    /// Required while the market has an allowlist
+    #[account(
+        seeds = [b"allowlist_pass", market.key().as_ref(), user.key().as_ref()],
+        bump = allowlist_pass.bump,
+    )]
+    pub allowlist_pass: Option<Account<'info, AllowlistPass>>,
+    
+    /// Required while the market blocks same-slot round trips
+    #[account(
+        mut,
+        seeds = [b"trader_state", market.key().as_ref(), user.key().as_ref()],
+        bump = trader_state.bump,
+    )]
+    pub trader_state: Option<Account<'info, TraderState>>,
+    
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(winning_outcome: Outcome)]
+pub struct ResolveMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Linked agent's market index, whose open-market slot this frees;
+    /// required when the market has an agent
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+    
+    /// Needed only when a delegated resolver signs
+    #[account(seeds = [b"market_roles", market.key().as_ref()], bump = roles.bump)]
+    pub roles: Option<Account<'info, MarketRoles>>,
+    
+    /// The market authority or its delegated resolver
+    #[account(
+        constraint = market.grants(roles.as_deref(), MarketRole::Resolver, &resolver.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFromAgents<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// Registry round keyed by this market's address
+    #[account(constraint = round.market_id == market.key().to_bytes() @ ErrorCode::RoundMarketMismatch)]
+    pub round: Account<'info, ResolutionRound>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Linked agent's market index, whose open-market slot this frees;
+    /// required when the market has an agent
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFromAgentProof<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        constraint = market.agent == Some(agent.key()) @ ErrorCode::AgentMismatch,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(constraint = agent.is_operator(&operator.key()) @ ErrorCode::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    
+    /// The agent's proof request keyed by this market's address
+    #[account(
+        has_one = agent @ ErrorCode::AgentMismatch,
+        constraint = proof_request.market_id == market.key().to_bytes() @ ErrorCode::ProofMarketMismatch,
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    
+    /// Agent wallet or one of its registered signing keys
+    pub operator: Signer<'info>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Linked agent's market index, whose open-market slot this frees
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+}
+
+#[derive(Accounts)]
+pub struct OpenAgentRound<'info> {
+    #[account(
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// CHECK: Created or reopened by the registry at `["round", market]`
+    #[account(
+        mut,
+        seeds = [b"round", market.key().as_ref()],
+        bump,
+        seeds::program = registry_program.key(),
+    )]
+    pub round: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub registry_program: Program<'info, AgentRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitOutcome<'info> {
+    #[account(
+        mut,
+        constraint = market.agent == Some(agent.key()) @ ErrorCode::AgentMismatch,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(constraint = agent.is_operator(&operator.key()) @ ErrorCode::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    
+    /// Agent wallet or one of its registered signing keys
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealOutcome<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Linked agent's market index, whose open-market slot this frees;
+    /// required when the market has an agent
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+}
+
+#[derive(Accounts)]
+pub struct PostResolutionMessage<'info> {
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ResolutionMessage::INIT_SPACE,
+        seeds = [b"resolution_message", market.key().as_ref()],
+        bump
+    )]
+    pub message: Account<'info, ResolutionMessage>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppendResolutionNote<'info> {
+    pub market: Account<'info, Market>,
+    
+    /// Lets committee resolvers append; omit otherwise
+    #[account(
+        seeds = [b"committee", market.key().as_ref()],
+        bump = committee.bump,
+    )]
+    pub committee: Option<Account<'info, Committee>>,
+    
+    #[account(
+        init_if_needed,
+        payer = author,
+        space = ResolutionNotes::space(0),
+        seeds = [b"resolution_notes", market.key().as_ref()],
+        bump
+    )]
+    pub notes: Account<'info, ResolutionNotes>,
+    
+    #[account(mut)]
+    pub author: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureCommittee<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Committee::INIT_SPACE,
+        seeds = [b"committee", market.key().as_ref()],
+        bump
+    )]
+    pub committee: Account<'info, Committee>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoteResolution<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"committee", market.key().as_ref()],
+        bump = committee.bump,
+    )]
+    pub committee: Account<'info, Committee>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
+    
+    /// Linked agent's market index, whose open-market slot this frees;
+    /// required when the market has an agent
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+    
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct ClaimWinnings<'info> {
+    #[account(
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub user_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.yes_mint)]
+    pub user_yes_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.no_mint)]
+    pub user_no_account: Account<'info, TokenAccount>,
+    
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ClaimWinnings<'info> {
+    /// Burn `amount` winning tokens and pay the same in collateral
+    fn redeem(&self, amount: u64) -> Result<()> {
+        require!(!self.config.disable_claim, ErrorCode::InstructionPaused);
+        let market = &self.market;
+        
+        require!(market.is_resolved, ErrorCode::MarketNotResolved);
+        require!(
+            Clock::get()?.unix_timestamp >= market.claim_enabled_at,
+            ErrorCode::ClaimsNotEnabled
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            self.collateral_vault.amount >= amount,
+            ErrorCode::ReserveVaultMismatch
+        );
+
+        let winning_outcome = market.winning_side().ok_or(ErrorCode::MarketNotResolved)?;
+
+        // Burn winning tokens
+        match winning_outcome {
+            Outcome::Yes => {
+                let cpi_accounts = Burn {
+                    mint: self.yes_mint.to_account_info(),
+                    from: self.user_yes_account.to_account_info(),
+                    authority: self.user.to_account_info(),
+                };
+                let cpi_program = self.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+                token::burn(cpi_ctx, amount)?;
+            }
+            Outcome::No => {
+                let cpi_accounts = Burn {
+                    mint: self.no_mint.to_account_info(),
+                    from: self.user_no_account.to_account_info(),
+                    authority: self.user.to_account_info(),
+                };
+                let cpi_program = self.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+                token::burn(cpi_ctx, amount)?;
+            }
+        }
+
+        // Transfer collateral to winner
+        let seeds = &[
+            b"market".as_ref(),
+            market.authority.as_ref(),
+            &market.market_id.to_le_bytes(),
+            &[market.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: self.collateral_vault.to_account_info(),
+            to: self.user_collateral.to_account_info(),
+            authority: market.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        token::transfer(cpi_ctx, amount)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithReference<'info> {
+    pub claim: ClaimWinnings<'info>,
+    
+    /// CHECK: SPL memo program
+    #[account(address = MEMO_PROGRAM_ID)]
+    pub memo_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMany<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, max_tip: u64, expires_at: i64)]
+pub struct ClaimFor<'info> {
+    #[account(
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    /// The market's mint for the winning side
+    #[account(mut)]
+    pub winning_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    /// CHECK: Identified by the Ed25519 signature verified in the handler
+    pub user: UncheckedAccount<'info>,
+    
+    #[account(mut, token::mint = winning_mint, token::authority = user)]
+    pub user_winning_account: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint, token::authority = user)]
+    pub user_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub relayer_collateral: Account<'info, TokenAccount>,
+    
+    /// Single-use marker for this exact authorization
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + ClaimAuthorization::INIT_SPACE,
+        seeds = [
+            b"claim_auth",
+            hash(&claim_for_message(&market.key(), &user.key(), amount, max_tip, expires_at)).as_ref(),
+        ],
+        bump
+    )]
+    pub authorization: Account<'info, ClaimAuthorization>,
+    
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    /// CHECK: Instructions sysvar, used to read the Ed25519 verification
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteMarket<'info> {
+    pub market: Account<'info, Market>,
+    
+    #[account(address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct GetProgramInfo {}
+
+#[derive(Accounts)]
+pub struct SolvencyCheck<'info> {
+    pub market: Account<'info, Market>,
+    
+    #[account(address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub authority_collateral: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSurplus<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(address = market.no_mint)]
     pub no_mint: Account<'info, Mint>,
     
-    #[account(mut)]
-    pub collateral_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub authority_collateral: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct GcMarket<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"market_index", question_hash(&market.question).as_ref()],
+        bump = market_index.bump,
+    )]
+    pub market_index: Account<'info, MarketIndex>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = market.collateral_mint,
+        token::authority = authority,
+    )]
+    pub authority_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, seeds = [b"market_list"], bump = market_list.bump)]
+    pub market_list: Account<'info, MarketList>,
+    
+    #[account(
+        mut,
+        seeds = [b"market_list".as_ref(), &market.list_page.to_le_bytes()],
+        bump = market_list_page.bump,
+    )]
+    pub market_list_page: Account<'info, MarketListPage>,
     
+    /// Linked agent's market index, whose open-market slot this frees;
+    /// required when the market has an agent
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Option<Account<'info, AgentMarkets>>,
+    
+    /// CHECK: The market creator; receives the closed accounts' rent
     #[account(mut)]
-    pub user_collateral: Account<'info, TokenAccount>,
+    pub authority: UncheckedAccount<'info>,
     
     #[account(mut)]
+    pub caller: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileSupply<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct CheckpointMarket<'info> {
+    #[account(
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+}
+
+#[cfg(not(feature = "mainnet"))]
+#[derive(Accounts)]
+pub struct Faucet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Account<'info, Mint>,
+    
+    #[account(mut, address = market.collateral_vault)]
+    pub collateral_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.collateral_mint)]
+    pub sponsor_collateral: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = market.yes_mint, token::authority = user)]
     pub user_yes_account: Account<'info, TokenAccount>,
     
-    #[account(mut)]
+    #[account(mut, token::mint = market.no_mint, token::authority = user)]
     pub user_no_account: Account<'info, TokenAccount>,
     
+    #[account(mut)]
+    pub user: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WrapPosition<'info> {
+    pub market: Account<'info, Market>,
+    
+    /// The market's YES or NO mint, matching the wrapped outcome
+    pub outcome_mint: Account<'info, Mint>,
+    
+    #[account(mut, token::mint = outcome_mint)]
+    pub user_outcome_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", receipt_mint.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = position,
+    )]
+    pub receipt_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = user,
+        token::mint = receipt_mint,
+        token::authority = user,
+    )]
+    pub user_receipt_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"position_escrow", receipt_mint.key().as_ref()],
+        bump,
+        token::mint = outcome_mint,
+        token::authority = position,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
     pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64, outcome: Outcome)]
-pub struct SellTokens<'info> {
+pub struct UnwrapPosition<'info> {
+    #[account(
+        mut,
+        close = holder,
+        seeds = [b"position", receipt_mint.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+    
+    #[account(mut, address = position.receipt_mint)]
+    pub receipt_mint: Account<'info, Mint>,
+    
+    #[account(mut, token::mint = receipt_mint, token::authority = holder)]
+    pub holder_receipt_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"position_escrow", receipt_mint.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = escrow.mint)]
+    pub holder_outcome_account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeAgentMarket<'info> {
+    #[account(
+        mut,
+        constraint = market.agent == Some(agent.key()) @ ErrorCode::AgentMismatch,
+    )]
+    pub market: Account<'info, Market>,
+    
+    #[account(constraint = agent.compromised @ ErrorCode::AgentNotCompromised)]
+    pub agent: Account<'info, Agent>,
+}
+
+#[derive(Accounts)]
+pub struct PauseMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"market_roles", market.key().as_ref()], bump = roles.bump)]
+    pub roles: Option<Account<'info, MarketRoles>>,
+    
+    #[account(
+        constraint = market.grants(roles.as_deref(), MarketRole::Pauser, &pauser.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub pauser: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnfreezeMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+    
+    #[account(seeds = [b"market_roles", market.key().as_ref()], bump = roles.bump)]
+    pub roles: Option<Account<'info, MarketRoles>>,
+    
+    #[account(
+        constraint = market.grants(roles.as_deref(), MarketRole::Pauser, &pauser.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub pauser: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMarketRoles<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + MarketRoles::INIT_SPACE,
+        seeds = [b"market_roles", market.key().as_ref()],
+        bump
+    )]
+    pub roles: Account<'info, MarketRoles>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoundTripGuard<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenTraderState<'info> {
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init,
+        payer = trader,
+        space = 8 + TraderState::INIT_SPACE,
+        seeds = [b"trader_state", market.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub trader_state: Account<'info, TraderState>,
+    
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAgentLookupTable<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent_markets", agent.key().as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Account<'info, AgentMarkets>,
+    
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    
+    /// CHECK: Derived from agent_markets and recent_slot; created by the lookup table program
+    #[account(mut)]
+    pub lookup_table: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Address lookup table program
+    #[account(address = alt_program::ID)]
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendAgentLookupTable<'info> {
+    #[account(
+        seeds = [b"agent_markets", agent_markets.agent.as_ref()],
+        bump = agent_markets.bump,
+    )]
+    pub agent_markets: Account<'info, AgentMarkets>,
+    
     #[account(
         mut,
         seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
         bump = market.bump,
+        has_one = authority @ ErrorCode::Unauthorized,
+        constraint = market.agent == Some(agent_markets.agent) @ ErrorCode::AgentMismatch,
+    )]
+    pub market: Account<'info, Market>,
+    
+    /// CHECK: Must be the table recorded on agent_markets
+    #[account(
+        mut,
+        constraint = agent_markets.lookup_table == Some(lookup_table.key()) @ ErrorCode::LookupTableMismatch,
+    )]
+    pub lookup_table: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Address lookup table program
+    #[account(address = alt_program::ID)]
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinTrade<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowlist<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct JoinAllowlist<'info> {
+    pub market: Account<'info, Market>,
+    
+    // Re-joining after the root changes refreshes the pass
+    #[account(
+        init_if_needed,
+        payer = trader,
+        space = 8 + AllowlistPass::INIT_SPACE,
+        seeds = [b"allowlist_pass", market.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub allowlist_pass: Account<'info, AllowlistPass>,
+    
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetOpensAt<'info> {
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTranslation<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Translations::INIT_SPACE,
+        seeds = [b"translations", market.key().as_ref()],
+        bump
+    )]
+    pub translations: Account<'info, Translations>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveTranslation<'info> {
+    #[account(has_one = authority @ ErrorCode::Unauthorized)]
+    pub market: Account<'info, Market>,
+    
+    #[account(
+        mut,
+        seeds = [b"translations", market.key().as_ref()],
+        bump = translations.bump,
     )]
+    pub translations: Account<'info, Translations>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(airdrop_id: u64)]
+pub struct CreateAirdrop<'info> {
     pub market: Account<'info, Market>,
     
-    #[account(mut)]
-    pub yes_mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub no_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + Airdrop::INIT_SPACE,
+        seeds = [b"airdrop", market.key().as_ref(), &airdrop_id.to_le_bytes()],
+        bump
+    )]
+    pub airdrop: Account<'info, Airdrop>,
     
-    #[account(mut)]
-    pub collateral_vault: Account<'info, TokenAccount>,
+    pub reward_mint: Account<'info, Mint>,
     
-    #[account(mut)]
-    pub user_collateral: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = sponsor,
+        token::mint = reward_mint,
+        token::authority = airdrop,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
     
-    #[account(mut)]
-    pub user_yes_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = reward_mint)]
+    pub sponsor_tokens: Account<'info, TokenAccount>,
     
     #[account(mut)]
-    pub user_no_account: Account<'info, TokenAccount>,
+    pub sponsor: Signer<'info>,
     
-    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(winning_outcome: Outcome)]
-pub struct ResolveMarket<'info> {
+#[instruction(index: u64)]
+pub struct ClaimAirdrop<'info> {
     #[account(
         mut,
-        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
-        bump = market.bump,
+        seeds = [b"airdrop", airdrop.market.as_ref(), &airdrop.airdrop_id.to_le_bytes()],
+        bump = airdrop.bump,
     )]
-    pub market: Account<'info, Market>,
+    pub airdrop: Account<'info, Airdrop>,
     
-    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + AirdropReceipt::INIT_SPACE,
+        seeds = [b"airdrop_receipt", airdrop.key().as_ref(), &index.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Account<'info, AirdropReceipt>,
+    
+    #[account(mut, address = airdrop.reward_vault)]
+    pub reward_vault: Account<'info, TokenAccount>,
+    
+    #[account(mut, token::mint = airdrop.reward_mint)]
+    pub claimant_tokens: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(amount: u64)]
-pub struct ClaimWinnings<'info> {
+pub struct InitializeConfig<'info> {
     #[account(
-        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
-        bump = market.bump,
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
     )]
-    pub market: Account<'info, Market>,
-    
-    #[account(mut)]
-    pub yes_mint: Account<'info, Mint>,
+    pub config: Account<'info, Config>,
     
-    #[account(mut)]
-    pub no_mint: Account<'info, Mint>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ ErrorCode::InvalidProgramData)]
+    pub program: Program<'info, crate::program::PredictionMarket>,
     
-    #[account(mut)]
-    pub collateral_vault: Account<'info, TokenAccount>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
     
     #[account(mut)]
-    pub user_collateral: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
     
-    #[account(mut)]
-    pub user_yes_account: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
     
-    #[account(mut)]
-    pub user_no_account: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
     
-    pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub admin: Signer<'info>,
+}
+
+/// Protocol-wide settings, at `["config"]`
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub disable_create: bool,
+    /// Blocks buys only; sells stay open as an exit
+    pub disable_trade: bool,
+    pub disable_resolve: bool,
+    pub disable_claim: bool,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Market {
+    pub authority: Pubkey,
+    pub market_id: u64,
+    /// Registry agent the market is about, if linked
+    pub agent: Option<Pubkey>,
+    #[max_len(MAX_QUESTION)]
+    pub question: String,
+    pub yes_mint: Pubkey,
+    pub no_mint: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub collateral_vault: Pubkey,
+    /// Merkle root over permitted traders; None means anyone may trade
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Trading starts at this unix time; 0 means immediately
+    pub opens_at: i64,
+    pub end_time: i64,
+    pub resolution_mode: ResolutionMode,
+    pub is_resolved: bool,
+    pub winning_outcome: Option<Outcome>,
+    /// Unix time from which winners may claim; 0 means as soon as resolved
+    pub claim_enabled_at: i64,
+    pub transcript_hash: [u8; 32],
+    #[max_len(MAX_TRANSCRIPT_URI)]
+    pub transcript_uri: String,
+    pub total_yes_supply: u64,
+    pub total_no_supply: u64,
+    /// Reject a buy and sell of the same side by one wallet within a slot
+    pub block_round_trips: bool,
+    /// Trading and resolution halted, e.g. because the linked agent was compromised
+    pub frozen: bool,
+    /// Smallest buy/sell size in collateral base units; 0 disables the floor
+    pub min_trade_amount: u64,
+    /// Linked agent's `outcome_commitment` hash, sealed before end_time
+    pub outcome_commitment: Option<[u8; 32]>,
+    /// Page of the global market list this market was appended to
+    pub list_page: u32,
+    /// Buys, sells and rebalance legs executed; drives periodic checkpoints
+    pub trade_count: u64,
+    /// Collateral ever moved by trades and faucet mints; gc_market needs it at 0
+    pub volume: u64,
+    /// Accounts already appended to the agent's lookup table
+    pub in_lookup_table: bool,
+    /// Collateral the authority deposited without taking tokens for it
+    /// (add_liquidity, the unused half of faucet sponsorship); caps withdraw_surplus
+    pub creator_liquidity: u64,
+    pub bump: u8,
+}
+
+// Markets are created through a CPI-capable init, which caps a new account at 10 KiB
+const _: () = assert!(8 + Market::INIT_SPACE <= 10_240);
+
+impl Market {
+    /// Final step shared by every resolution source; trade and claim logic
+    /// only ever look at the result. Going against a lopsided book is more
+    /// likely a mistake or manipulation than news, so whoever resolves, claims
+    /// then wait out a dispute window of CONTESTED_DISPUTE_SECS from `now`.
+    pub fn settle(&mut self, outcome: Outcome, claim_enabled_at: i64, now: i64) -> Result<()> {
+        let claim_enabled_at = if self.is_against_supply(outcome) {
+            let min_claim_at = now.checked_add(CONTESTED_DISPUTE_SECS).ok_or(ErrorCode::Overflow)?;
+            claim_enabled_at.max(min_claim_at)
+        } else {
+            claim_enabled_at
+        };
+        self.is_resolved = true;
+        self.winning_outcome = Some(outcome);
+        self.claim_enabled_at = claim_enabled_at;
+        Ok(())
+    }
+
+    /// Settle on `source`'s outcome, provided the market was created to
+    /// resolve from that kind of source.
+    pub fn resolve_from<S: ResolutionSource>(
+        &mut self,
+        source: &S,
+        claim_enabled_at: i64,
+        now: i64,
+    ) -> Result<Outcome> {
+        require!(self.resolution_mode == S::MODE, ErrorCode::WrongResolutionMode);
+        let outcome = source.outcome()?;
+        self.settle(outcome, claim_enabled_at, now)?;
+        Ok(outcome)
+    }
+
+    /// Count `trades` more trades moving `volume` collateral; true when that
+    /// crosses a multiple of CHECKPOINT_INTERVAL and a checkpoint is due.
+    pub fn record_trades(&mut self, trades: u64, volume: u64) -> Result<bool> {
+        self.volume = self.volume.checked_add(volume).ok_or(ErrorCode::Overflow)?;
+        let before = self.trade_count;
+        self.trade_count = before.checked_add(trades).ok_or(ErrorCode::Overflow)?;
+        Ok(before / CHECKPOINT_INTERVAL != self.trade_count / CHECKPOINT_INTERVAL)
+    }
+
+    /// Snapshot for indexers to resync from without replaying every trade
+    pub fn checkpoint(&self, market: Pubkey, vault_balance: u64, slot: u64) -> MarketStateCheckpoint {
+        MarketStateCheckpoint {
+            market,
+            slot,
+            trade_count: self.trade_count,
+            vault_balance,
+            total_yes_supply: self.total_yes_supply,
+            total_no_supply: self.total_no_supply,
+            is_resolved: self.is_resolved,
+            winning_outcome: self.winning_outcome,
+            frozen: self.frozen,
+        }
+    }
+
+    /// A committed agent gets the first REVEAL_WINDOW_SECS after end_time to
+    /// resolve by reveal; every other path waits that out.
+    pub fn awaiting_reveal(&self, now: i64) -> bool {
+        self.outcome_commitment.is_some()
+            && now < self.end_time.saturating_add(REVEAL_WINDOW_SECS)
+    }
+
+    /// Whether `signer` holds `role`; the authority holds every role
+    pub fn grants(&self, roles: Option<&MarketRoles>, role: MarketRole, signer: &Pubkey) -> bool {
+        if *signer == self.authority {
+            return true;
+        }
+        let holder = roles.and_then(|roles| match role {
+            MarketRole::Resolver => roles.resolver,
+            MarketRole::Pauser => roles.pauser,
+        });
+        holder == Some(*signer)
+    }
+
+    /// Market-side checks shared by buy_tokens, sell_tokens and each mm_rebalance leg
+    pub fn check_trade(&self, pass: Option<&AllowlistPass>, now: i64, amount: u64) -> Result<()> {
+        require!(!self.is_resolved, ErrorCode::MarketResolved);
+        require!(!self.frozen, ErrorCode::MarketFrozen);
+        require!(self.admits(pass), ErrorCode::NotAllowlisted);
+        require!(now >= self.opens_at, ErrorCode::MarketNotOpen);
+        require!(now < self.end_time, ErrorCode::MarketEnded);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount >= self.min_trade_amount, ErrorCode::TradeTooSmall);
+        Ok(())
+    }
+
+    /// Whether a trader holding `pass` may trade; passes issued under an
+    /// older root stop working when the allowlist is replaced.
+    pub fn admits(&self, pass: Option<&AllowlistPass>) -> bool {
+        match self.allowlist_root {
+            None => true,
+            Some(root) => pass.is_some_and(|pass| pass.root == root),
+        }
+    }
+
+    /// The winning outcome once resolved; clients should use this rather than
+    /// reading `is_resolved` and `winning_outcome` separately.
+    pub fn winning_side(&self) -> Option<Outcome> {
+        if self.is_resolved {
+            self.winning_outcome
+        } else {
+            None
+        }
+    }
+
+    /// True when the other side holds more than LOPSIDED_SUPPLY_BPS of outstanding supply
+    pub fn is_against_supply(&self, outcome: Outcome) -> bool {
+        let total = self.total_yes_supply as u128 + self.total_no_supply as u128;
+        if total == 0 {
+            return false;
+        }
+        let other = match outcome {
+            Outcome::Yes => self.total_no_supply,
+            Outcome::No => self.total_yes_supply,
+        } as u128;
+        other * 10_000 > total * LOPSIDED_SUPPLY_BPS
+    }
+}
+
+/// Powers the market authority has delegated, at `["market_roles", market]`
+#[account]
+#[derive(InitSpace)]
+pub struct MarketRoles {
+    pub market: Pubkey,
+    pub resolver: Option<Pubkey>,
+    pub pauser: Option<Pubkey>,
+    pub bump: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MarketRole {
+    Resolver,
+    Pauser,
+}
+
+/// A scheduled match between two registry agents, at
+/// `["battle", authority, battle_id (u64 LE)]`; its market shares the id.
+#[account]
+#[derive(InitSpace)]
+pub struct Battle {
+    pub authority: Pubkey,
+    pub battle_id: u64,
+    pub agent_a: Pubkey,
+    pub agent_b: Pubkey,
+    pub market: Pubkey,
+    pub scheduled_at: i64,
+    pub bump: u8,
+}
+
+/// Reusable question wording, at `["template", authority, template_id (u64 LE)]`.
+/// `{name}` in the body is a placeholder for the variable of that name.
+#[account]
+#[derive(InitSpace)]
+pub struct Template {
+    pub authority: Pubkey,
+    pub template_id: u64,
+    #[max_len(MAX_QUESTION)]
+    pub body: String,
+    #[max_len(MAX_TEMPLATE_VARS, MAX_TEMPLATE_VAR_NAME)]
+    pub variables: Vec<String>,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MarketIndex {
+    pub market: Pubkey,
+    pub bump: u8,
+}
+
+/// Directory of the markets linked to a registry agent, at
+/// `["agent_markets", agent]`; markets live in fixed-size pages at
+/// `["agent_markets", agent, page (u32 LE)]`.
+#[account]
+#[derive(InitSpace)]
+pub struct AgentMarkets {
+    pub agent: Pubkey,
+    pub market_count: u64,
+    /// Linked markets not yet resolved or collected; capped by the agent's tier
+    pub open_markets: u32,
+    /// Address lookup table of this agent's market accounts, owned by this PDA
+    pub lookup_table: Option<Pubkey>,
+    pub bump: u8,
+}
+
+impl AgentMarkets {
+    pub fn current_page(&self) -> u32 {
+        (self.market_count / MARKETS_PER_PAGE as u64) as u32
+    }
+
+    /// Record a new market linked to `agent` on `page`, which must be
+    /// `current_page()`; both accounts may have just been created. Fails
+    /// once the agent has `max_open_markets` unresolved markets.
+    pub fn append(
+        &mut self,
+        page: &mut AgentMarketsPage,
+        agent: Pubkey,
+        market: Pubkey,
+        max_open_markets: u16,
+        bump: u8,
+        page_bump: u8,
+    ) -> Result<()> {
+        require!(
+            self.open_markets < u32::from(max_open_markets),
+            ErrorCode::TooManyOpenMarkets
+        );
+        self.open_markets += 1;
+        if self.market_count == 0 {
+            self.agent = agent;
+            self.lookup_table = None;
+            self.bump = bump;
+        }
+        if page.markets.is_empty() {
+            page.page = self.current_page();
+            page.bump = page_bump;
+        }
+        page.markets.push(market);
+        self.market_count = self.market_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AgentMarketsPage {
+    pub page: u32,
+    #[max_len(MARKETS_PER_PAGE)]
+    pub markets: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Global directory of unresolved markets, for clients whose RPC disables
+/// getProgramAccounts. Markets are appended to pages at
+/// `["market_list", page (u32 LE)]` on creation and removed on resolution, so
+/// older pages thin out over time but page numbers never change.
+#[account]
+#[derive(InitSpace)]
+pub struct MarketList {
+    /// Markets ever appended; selects the page being filled
+    pub listed: u64,
+    /// Markets currently listed
+    pub active: u64,
+    pub bump: u8,
+}
+
+impl MarketList {
+    pub fn current_page(&self) -> u32 {
+        (self.listed / MARKETS_PER_PAGE as u64) as u32
+    }
+
+    pub fn delist(&mut self, page: &mut MarketListPage, market: Pubkey) -> Result<()> {
+        let before = page.markets.len();
+        page.markets.retain(|listed| *listed != market);
+        if page.markets.len() < before {
+            self.active = self.active.checked_sub(1).ok_or(ErrorCode::Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MarketListPage {
+    pub page: u32,
+    #[max_len(MARKETS_PER_PAGE)]
+    pub markets: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Outcome tokens escrowed behind a receipt mint, at `["position", receipt_mint]`.
+/// Outcome tokens are minted 1:1 against collateral, so `size` is also the
+/// position's entry cost in collateral base units.
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub market: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub outcome: Outcome,
+    pub size: u64,
+    pub opened_at: i64,
+    pub bump: u8,
+}
+
+/// Resolver set of a `Committee` market, at `["committee", market]`. Votes are
+/// bitmaps indexed by member position.
+#[account]
+#[derive(InitSpace)]
+pub struct Committee {
+    pub market: Pubkey,
+    #[max_len(MAX_COMMITTEE)]
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub yes_votes: u8,
+    pub no_votes: u8,
+    pub bump: u8,
+}
+
+/// Per-wallet trade slots for round-trip blocking, at
+/// `["trader_state", market, trader]`; indexed by side (0 = Yes, 1 = No)
+#[account]
+#[derive(InitSpace)]
+pub struct TraderState {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub last_buy_slot: [u64; 2],
+    pub last_sell_slot: [u64; 2],
+    pub bump: u8,
+}
+
+impl TraderState {
+    /// Record a trade, rejecting it if it reverses a same-side trade from this slot
+    pub fn record_trade(&mut self, slot: u64, outcome: Outcome, is_buy: bool) -> Result<()> {
+        let side = match outcome {
+            Outcome::Yes => 0,
+            Outcome::No => 1,
+        };
+        let (opposite, own) = if is_buy {
+            (self.last_sell_slot[side], &mut self.last_buy_slot[side])
+        } else {
+            (self.last_buy_slot[side], &mut self.last_sell_slot[side])
+        };
+        require!(opposite != slot, ErrorCode::RoundTripBlocked);
+        *own = slot;
+        Ok(())
+    }
+}
+
+/// A trader's proven membership in a market allowlist, at
+/// `["allowlist_pass", market, trader]`
+#[account]
+#[derive(InitSpace)]
+pub struct AllowlistPass {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    /// Allowlist root the membership was proven against
+    pub root: [u8; 32],
+    pub bump: u8,
+}
+
+/// Marks a claim_for authorization as used, at `["claim_auth", hash(message)]`
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimAuthorization {
+    pub user: Pubkey,
+    pub market: Pubkey,
+    pub bump: u8,
+}
+
+/// Localized copies of a market's question at `["translations", market]`,
+/// maintained by the market authority.
+#[account]
+#[derive(InitSpace)]
+pub struct Translations {
+    pub market: Pubkey,
+    #[max_len(MAX_TRANSLATIONS)]
+    pub entries: Vec<Translation>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, PartialEq, Eq)]
+pub struct Translation {
+    #[max_len(MAX_LANG_CODE)]
+    pub lang_code: String,
+    #[max_len(MAX_QUESTION)]
+    pub statement: String,
+}
+
+/// Sponsor-funded bonus distribution over a resolved market's holders, at
+/// `["airdrop", market, airdrop_id (u64 LE)]`
+#[account]
+#[derive(InitSpace)]
+pub struct Airdrop {
+    pub market: Pubkey,
+    pub airdrop_id: u64,
+    pub sponsor: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_vault: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub bump: u8,
+}
+
+/// Marks one airdrop leaf as claimed
+#[account]
+#[derive(InitSpace)]
+pub struct AirdropReceipt {
+    pub airdrop: Pubkey,
+    pub index: u64,
+    pub claimant: Pubkey,
+    pub bump: u8,
+}
+
+/// Passes a failed trade check through. With the `trade-rejection-events`
+/// feature it first emits `TradeRejected`: the transaction still fails, but
+/// its logs keep the event, so indexers can measure how often traders hit
+/// caps and gates.
+#[cfg_attr(not(feature = "trade-rejection-events"), allow(unused_variables))]
+fn trade_rejected(
+    error: Error,
+    market: Pubkey,
+    user: Pubkey,
+    outcome: Outcome,
+    amount: u64,
+    is_buy: bool,
+) -> Error {
+    #[cfg(feature = "trade-rejection-events")]
+    {
+        let error_code = match &error {
+            Error::AnchorError(e) => e.error_code_number,
+            Error::ProgramError(_) => 0,
+        };
+        emit!(TradeRejected {
+            market,
+            user,
+            outcome,
+            amount,
+            is_buy,
+            error_code,
+        });
+    }
+    error
+}
+
+/// Leaf committed to by an airdrop's merkle root. Leaves and inner nodes are
+/// domain-separated (0x00 / 0x01 prefix) so a node can't be passed off as a leaf.
+pub fn airdrop_leaf(index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[&[0u8], &index.to_le_bytes(), claimant.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// Bytes a user signs to let a relayer claim for them
+pub fn claim_for_message(
+    market: &Pubkey,
+    user: &Pubkey,
+    amount: u64,
+    max_tip: u64,
+    expires_at: i64,
+) -> Vec<u8> {
+    [
+        b"prompt-wars:claim_for".as_ref(),
+        market.as_ref(),
+        user.as_ref(),
+        &amount.to_le_bytes(),
+        &max_tip.to_le_bytes(),
+        &expires_at.to_le_bytes(),
+    ]
+    .concat()
+}
+
+/// Whether `ix` is an Ed25519 program instruction verifying exactly one
+/// signature by `signer` over `message`, with all data inline.
+fn is_ed25519_signature(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> bool {
+    let data = &ix.data;
+    if ix.program_id != ed25519_program::ID || data.len() < 16 || data[0] != 1 {
+        return false;
+    }
+    let read = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    // Offsets must point into this same instruction (index u16::MAX)
+    if read(4) != u16::MAX || read(8) != u16::MAX || read(14) != u16::MAX {
+        return false;
+    }
+    let pubkey_at = read(6) as usize;
+    let message_at = read(10) as usize;
+    let message_len = read(12) as usize;
+    data.get(pubkey_at..pubkey_at + 32) == Some(signer.as_ref())
+        && data.get(message_at..message_at + message_len) == Some(message)
+}
+
+/// Allowlist leaf for a trader; same domain separation as `airdrop_leaf`
+pub fn allowlist_leaf(trader: &Pubkey) -> [u8; 32] {
+    hashv(&[&[0u8], trader.as_ref()]).to_bytes()
+}
+
+/// Sorted-pair merkle verification, so proofs carry no left/right flags
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (a, b) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        hashv(&[&[1u8], &a, &b]).to_bytes()
+    });
+    computed == root
+}
+
+/// Append-only resolution log of a market. Sized to its current length and
+/// grown one entry per append, so it has no `InitSpace`.
+#[account]
+pub struct ResolutionNotes {
+    pub market: Pubkey,
+    pub notes: Vec<ResolutionNote>,
+    pub bump: u8,
+}
+
+impl ResolutionNotes {
+    pub const fn space(len: usize) -> usize {
+        8 + 32 + 4 + len * ResolutionNote::INIT_SPACE + 1
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, PartialEq, Eq)]
+pub struct ResolutionNote {
+    pub author: Pubkey,
+    pub note_hash: [u8; 32],
+    #[max_len(MAX_NOTE_URI)]
+    pub uri: String,
+    pub posted_at: i64,
 }
 
+/// Cross-chain copy of a market's resolution
 #[account]
-pub struct Market {
-    pub authority: Pubkey,
-    pub market_id: u64,
-    pub question: String,
-    pub yes_mint: Pubkey,
-    pub no_mint: Pubkey,
-    pub collateral_vault: Pubkey,
-    pub end_time: i64,
-    pub is_resolved: bool,
-    pub winning_outcome: Option<Outcome>,
-    pub total_yes_supply: u64,
-    pub total_no_supply: u64,
+#[derive(InitSpace)]
+pub struct ResolutionMessage {
+    pub market: Pubkey,
+    pub payload: [u8; RESOLUTION_PAYLOAD_LEN],
+    pub posted_at: i64,
     pub bump: u8,
 }
 
-impl Market {
-    pub const LEN: usize = 32 + // authority
-        8 + // market_id
-        (4 + 200) + // question (max 200 chars)
-        32 + // yes_mint
-        32 + // no_mint
-        32 + // collateral_vault
-        8 + // end_time
-        1 + // is_resolved
-        (1 + 1) + // winning_outcome (Option<Outcome>)
-        8 + // total_yes_supply
-        8 + // total_no_supply
-        1; // bump
+/// Big-endian, fixed-offset encoding for EVM-side decoders:
+/// version u8 | market [32] | market_id u64 | outcome u8 (1 = Yes, 0 = No) |
+/// transcript_hash [32] | claim_enabled_at i64
+pub fn resolution_payload(
+    market_key: &Pubkey,
+    market: &Market,
+    winning_outcome: Outcome,
+) -> [u8; RESOLUTION_PAYLOAD_LEN] {
+    let mut payload = [0u8; RESOLUTION_PAYLOAD_LEN];
+    payload[0] = RESOLUTION_PAYLOAD_VERSION;
+    payload[1..33].copy_from_slice(market_key.as_ref());
+    payload[33..41].copy_from_slice(&market.market_id.to_be_bytes());
+    payload[41] = match winning_outcome {
+        Outcome::Yes => 1,
+        Outcome::No => 0,
+    };
+    payload[42..74].copy_from_slice(&market.transcript_hash);
+    payload[74..82].copy_from_slice(&market.claim_enabled_at.to_be_bytes());
+    payload
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+/// Commitment the linked agent seals with commit_outcome:
+/// sha256(outcome (0 = Yes, 1 = No) || salt)
+pub fn outcome_commitment(outcome: Outcome, salt: &[u8; 32]) -> [u8; 32] {
+    let outcome_byte = match outcome {
+        Outcome::Yes => 0u8,
+        Outcome::No => 1u8,
+    };
+    hashv(&[&[outcome_byte], salt]).to_bytes()
+}
+
+/// Fill each `{name}` placeholder in `body` with the value at that name's
+/// position in `names`. None if the counts differ, a value is empty, or the
+/// body has an undeclared or unterminated placeholder or a stray `}`.
+pub fn render_template(body: &str, names: &[String], values: &[String]) -> Option<String> {
+    if names.len() != values.len() || values.iter().any(|v| v.is_empty()) {
+        return None;
+    }
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(open) = rest.find('{') {
+        if rest[..open].contains('}') {
+            return None;
+        }
+        out.push_str(&rest[..open]);
+        let close = rest[open..].find('}')? + open;
+        let name = &rest[open + 1..close];
+        let i = names.iter().position(|n| n == name)?;
+        out.push_str(&values[i]);
+        rest = &rest[close + 1..];
+    }
+    if rest.contains('}') {
+        return None;
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Free the open-market slot a linked market held on its agent's index
+/// once it resolves or is collected.
+pub fn release_agent_slot(market: &Market, agent_markets: Option<&mut AgentMarkets>) -> Result<()> {
+    let Some(agent) = market.agent else {
+        return Ok(());
+    };
+    let agent_markets = agent_markets.ok_or(ErrorCode::AgentMarketsRequired)?;
+    require_keys_eq!(agent_markets.agent, agent, ErrorCode::AgentMismatch);
+    agent_markets.open_markets = agent_markets.open_markets.saturating_sub(1);
+    Ok(())
+}
+
+/// Seed for a question's MarketIndex PDA, so clients can derive it without a
+/// scan. Hashes the normalized form, so trivially different spellings of one
+/// question map to the same index and collide.
+pub fn question_hash(question: &str) -> [u8; 32] {
+    hash(normalize_question(question).as_bytes()).to_bytes()
+}
+
+/// Canonical form used for duplicate detection: lowercased, trimmed, and with
+/// every whitespace run collapsed to a single space. The stored question keeps
+/// the creator's original spelling.
+pub fn normalize_question(question: &str) -> String {
+    question
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Unicode general category Cf (format characters) as of Unicode 15.1:
+/// zero-width and joiner characters, bidi controls, soft hyphen, BOM and the
+/// like. Sorted, inclusive ranges.
+const FORMAT_CHARS: &[(char, char)] = &[
+    ('\u{00AD}', '\u{00AD}'),
+    ('\u{0600}', '\u{0605}'),
+    ('\u{061C}', '\u{061C}'),
+    ('\u{06DD}', '\u{06DD}'),
+    ('\u{070F}', '\u{070F}'),
+    ('\u{0890}', '\u{0891}'),
+    ('\u{08E2}', '\u{08E2}'),
+    ('\u{180E}', '\u{180E}'),
+    ('\u{200B}', '\u{200F}'),
+    ('\u{202A}', '\u{202E}'),
+    ('\u{2060}', '\u{2064}'),
+    ('\u{2066}', '\u{206F}'),
+    ('\u{FEFF}', '\u{FEFF}'),
+    ('\u{FFF9}', '\u{FFFB}'),
+    ('\u{110BD}', '\u{110BD}'),
+    ('\u{110CD}', '\u{110CD}'),
+    ('\u{13430}', '\u{1343F}'),
+    ('\u{1BCA0}', '\u{1BCA3}'),
+    ('\u{1D173}', '\u{1D17A}'),
+    ('\u{E0001}', '\u{E0001}'),
+    ('\u{E0020}', '\u{E007F}'),
+];
+
+fn is_format_char(c: char) -> bool {
+    FORMAT_CHARS
+        .binary_search_by(|&(lo, hi)| {
+            if hi < c {
+                std::cmp::Ordering::Less
+            } else if lo > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Questions and translations must have visible text and no control (Cc) or
+/// format (Cf) characters, such as zero-width spaces, bidi overrides, word
+/// joiners or the BOM. These break rendering and let look-alike questions
+/// dodge the index. Encoding needs no check here: borsh only decodes a String
+/// from valid UTF-8, which already excludes overlong forms.
+pub fn is_valid_statement(statement: &str) -> bool {
+    !statement.trim().is_empty() && !statement.chars().any(|c| c.is_control() || is_format_char(c))
+}
+
+/// Who decides a market's outcome
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq)]
+pub enum ResolutionMode {
+    /// The market authority calls resolve_market
+    Authority,
+    /// Bond-weighted majority of registry agents (agent_registry resolution round)
+    AgentConsensus,
+    /// M-of-N vote of a fixed resolver committee (configure_committee)
+    Committee,
+    /// The linked agent, backed by a proof it fulfilled for the market
+    /// (resolve_from_agent_proof)
+    AgentProof,
+}
+
+/// Resolution adapter: one place a market's outcome can come from. Each
+/// resolving instruction builds its source from its accounts and settles
+/// through `Market::resolve_from`, so a new source needs no changes to trade
+/// or claim logic.
+pub trait ResolutionSource {
+    /// Mode a market must have been created with to accept this source
+    const MODE: ResolutionMode;
+
+    /// Outcome the source vouches for, or why it can't vouch for one yet
+    fn outcome(&self) -> Result<Outcome>;
+}
+
+/// The market authority's own call (resolve_market)
+pub struct AuthorityDecision(pub Outcome);
+
+impl ResolutionSource for AuthorityDecision {
+    const MODE: ResolutionMode = ResolutionMode::Authority;
+
+    fn outcome(&self) -> Result<Outcome> {
+        Ok(self.0)
+    }
+}
+
+/// A finalized agent_registry resolution round
+impl ResolutionSource for ResolutionRound {
+    const MODE: ResolutionMode = ResolutionMode::AgentConsensus;
+
+    fn outcome(&self) -> Result<Outcome> {
+        require!(self.finalized, ErrorCode::NoAgentConsensus);
+        match self.outcome {
+            Some(Verdict::Yes) => Ok(Outcome::Yes),
+            Some(Verdict::No) => Ok(Outcome::No),
+            None => err!(ErrorCode::NoAgentConsensus),
+        }
+    }
+}
+
+/// The linked agent's outcome, valid only with a proof actually submitted;
+/// slashed requests are also marked fulfilled but carry none
+pub struct AgentProof<'a> {
+    pub request: &'a ProofRequest,
+    pub outcome: Outcome,
+}
+
+impl ResolutionSource for AgentProof<'_> {
+    const MODE: ResolutionMode = ResolutionMode::AgentProof;
+
+    fn outcome(&self) -> Result<Outcome> {
+        require!(
+            self.request.fulfilled && !self.request.proof_uri.is_empty(),
+            ErrorCode::RequestNotFulfilled
+        );
+        Ok(self.outcome)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq)]
 pub enum Outcome {
     Yes,
     No,
 }
 
+/// Per-market result of claim_many
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BatchClaim {
+    pub market: Pubkey,
+    pub status: BatchClaimStatus,
+    /// Collateral paid out; 0 unless `status` is `Claimed`
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BatchClaimStatus {
+    Claimed,
+    NotResolved,
+    ClaimsNotEnabled,
+    NothingToClaim,
+}
+
+/// One fill in an mm_rebalance batch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RebalanceLeg {
+    pub outcome: Outcome,
+    pub amount: u64,
+    pub is_buy: bool,
+}
+
+#[event]
+pub struct MarketCreatedFromRequest {
+    pub market: Pubkey,
+    pub agent: Pubkey,
+    pub request: Pubkey,
+    pub source_market_id: [u8; 32],
+}
+
+#[event]
+pub struct TemplateCreated {
+    pub template: Pubkey,
+    pub authority: Pubkey,
+    pub template_id: u64,
+}
+
+#[event]
+pub struct MarketCreatedFromTemplate {
+    pub market: Pubkey,
+    pub template: Pubkey,
+    pub values: Vec<String>,
+}
+
+#[event]
+pub struct BattleScheduled {
+    pub battle: Pubkey,
+    pub battle_id: u64,
+    pub agent_a: Pubkey,
+    pub agent_b: Pubkey,
+    pub market: Pubkey,
+}
+
+#[event]
+pub struct MarketCloned {
+    pub market: Pubkey,
+    pub source_market: Pubkey,
+    pub end_time: i64,
+}
+
+/// Only emitted with the `trade-rejection-events` feature, into the logs of
+/// the failing transaction
+#[event]
+pub struct TradeRejected {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub outcome: Outcome,
+    pub amount: u64,
+    pub is_buy: bool,
+    /// Anchor error code of the failed check
+    pub error_code: u32,
+}
+
 #[event]
 pub struct TokensPurchased {
     pub user: Pubkey,
     pub outcome: Outcome,
     pub amount: u64,
+    /// Partner frontend that routed the trade, if any
+    pub frontend_id: Option<u16>,
 }
 
 #[event]
@@ -458,18 +4509,211 @@ pub struct TokensSold {
     pub user: Pubkey,
     pub outcome: Outcome,
     pub amount: u64,
+    /// Partner frontend that routed the trade, if any
+    pub frontend_id: Option<u16>,
+}
+
+#[event]
+pub struct MarketRebalanced {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub legs: Vec<RebalanceLeg>,
+    pub collateral_in: u64,
+    pub collateral_out: u64,
+}
+
+#[event]
+pub struct OutcomeCommitted {
+    pub market: Pubkey,
+    pub agent: Pubkey,
+    pub commitment: [u8; 32],
 }
 
 #[event]
 pub struct MarketResolved {
     pub market: Pubkey,
     pub winning_outcome: Outcome,
+    pub transcript_hash: [u8; 32],
+    pub transcript_uri: String,
+    pub claim_enabled_at: i64,
+}
+
+#[event]
+pub struct AirdropCreated {
+    pub market: Pubkey,
+    pub airdrop: Pubkey,
+    pub reward_mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct ResolutionNoteAppended {
+    pub market: Pubkey,
+    pub author: Pubkey,
+    pub index: u32,
+    pub note_hash: [u8; 32],
+    pub uri: String,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub previous: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub disable_create: bool,
+    pub disable_trade: bool,
+    pub disable_resolve: bool,
+    pub disable_claim: bool,
+}
+
+#[event]
+pub struct AirdropClaimed {
+    pub airdrop: Pubkey,
+    pub claimant: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ResolutionMessagePosted {
+    pub market: Pubkey,
+    pub message: Pubkey,
+    pub payload: Vec<u8>,
+}
+
+#[event]
+pub struct PositionWrapped {
+    pub market: Pubkey,
+    pub position: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub owner: Pubkey,
+    pub outcome: Outcome,
+    pub size: u64,
+}
+
+#[event]
+pub struct PositionUnwrapped {
+    pub market: Pubkey,
+    pub position: Pubkey,
+    pub holder: Pubkey,
+    pub outcome: Outcome,
+    pub size: u64,
+}
+
+#[event]
+pub struct ClaimRelayed {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub relayer: Pubkey,
+    pub tip: u64,
+}
+
+#[event]
+pub struct MarketPaused {
+    pub market: Pubkey,
+    pub pauser: Pubkey,
+}
+
+#[event]
+pub struct MarketRolesSet {
+    pub market: Pubkey,
+    pub resolver: Option<Pubkey>,
+    pub pauser: Option<Pubkey>,
+}
+
+#[event]
+pub struct MarketFrozen {
+    pub market: Pubkey,
+    pub agent: Pubkey,
+}
+
+#[event]
+pub struct ReferencedPayout {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub user_collateral: Pubkey,
+    pub amount: u64,
+    /// sha256 of the memo attached to the payout
+    pub payout_reference: [u8; 32],
 }
 
 #[event]
 pub struct WinningsClaimed {
     pub user: Pubkey,
+    /// Owner of the collateral account that was paid
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Vault coverage of a market's outstanding claims; a negative surplus
+/// means the vault is short
+#[event]
+#[derive(Clone)]
+pub struct SolvencyReport {
+    pub market: Pubkey,
+    pub liabilities: u64,
+    pub vault_balance: u64,
+    pub surplus: i128,
+}
+
+/// Return value of `program_info`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProgramInfo {
+    /// Semantic version of the deployed crate
+    pub version: String,
+    pub features: u32,
+    pub config: Pubkey,
+}
+
+#[event]
+pub struct MarketCollected {
+    pub market: Pubkey,
+    pub caller: Pubkey,
+    /// Collateral returned to the creator
+    pub refund: u64,
+    /// Vault rent paid to the caller
+    pub tip: u64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub market: Pubkey,
+    pub amount: u64,
+    pub creator_liquidity: u64,
+}
+
+#[event]
+pub struct SurplusWithdrawn {
+    pub market: Pubkey,
     pub amount: u64,
+    pub remaining_liabilities: u64,
+}
+
+#[event]
+pub struct SupplyReconciled {
+    pub market: Pubkey,
+    pub total_yes_supply: u64,
+    pub total_no_supply: u64,
+    pub vault_balance: u64,
+}
+
+/// Full trading state of a market, emitted every CHECKPOINT_INTERVAL trades
+/// and by checkpoint_market
+#[event]
+pub struct MarketStateCheckpoint {
+    pub market: Pubkey,
+    pub slot: u64,
+    pub trade_count: u64,
+    pub vault_balance: u64,
+    pub total_yes_supply: u64,
+    pub total_no_supply: u64,
+    pub is_resolved: bool,
+    pub winning_outcome: Option<Outcome>,
+    pub frozen: bool,
 }
 
 #[error_code]
@@ -492,4 +4736,394 @@ pub enum ErrorCode {
     InvalidAmount,
     #[msg("Unauthorized")]
     Unauthorized,
-}
\ No newline at end of file
+    #[msg("Transcript URI is too long (max 200 characters)")]
+    TranscriptUriTooLong,
+    #[msg("Math overflow")]
+    Overflow,
+    #[msg("Collateral vault holds less than the requested payout")]
+    ReserveVaultMismatch,
+    #[msg("Instruction does not match the market's resolution mode")]
+    WrongResolutionMode,
+    #[msg("Agent resolution round has not reached a majority")]
+    NoAgentConsensus,
+    #[msg("Resolution round belongs to a different market")]
+    RoundMarketMismatch,
+    #[msg("Proof request has not been fulfilled with a proof")]
+    RequestNotFulfilled,
+    #[msg("Claims are not enabled for this market yet")]
+    ClaimsNotEnabled,
+    #[msg("Rebalance takes between 1 and 4 legs")]
+    InvalidLegCount,
+    #[msg("Collateral mint differs from the source market")]
+    CollateralMismatch,
+    #[msg("Language code must be 1-8 characters")]
+    InvalidLangCode,
+    #[msg("Market already has the maximum number of translations")]
+    TooManyTranslations,
+    #[msg("No translation for that language code")]
+    TranslationNotFound,
+    #[msg("Merkle proof does not match the airdrop root")]
+    InvalidMerkleProof,
+    #[msg("Claim exceeds the airdrop's remaining funds")]
+    AirdropExhausted,
+    #[msg("Trade is below the market's minimum size")]
+    TradeTooSmall,
+    #[msg("Outcome mint does not belong to this market side")]
+    OutcomeMintMismatch,
+    #[msg("Position is below the receipt threshold")]
+    PositionTooSmall,
+    #[msg("Market is not open for trading yet")]
+    MarketNotOpen,
+    #[msg("Market already has positions")]
+    MarketAlreadyTraded,
+    #[msg("Trader is not on the market's allowlist")]
+    NotAllowlisted,
+    #[msg("Market has no allowlist")]
+    NoAllowlist,
+    #[msg("Claim authorization has expired")]
+    ClaimAuthorizationExpired,
+    #[msg("Relayer tip exceeds the signed or protocol maximum")]
+    RelayerTipTooHigh,
+    #[msg("Winning tokens are not delegated to the market")]
+    ClaimNotDelegated,
+    #[msg("Missing or invalid Ed25519 claim signature")]
+    InvalidClaimSignature,
+    #[msg("Market is frozen")]
+    MarketFrozen,
+    #[msg("Market is not linked to this agent")]
+    AgentMismatch,
+    #[msg("Agent is not flagged as compromised")]
+    AgentNotCompromised,
+    #[msg("Vault holds nothing beyond the winning supply")]
+    NoSurplus,
+    #[msg("Market blocks round trips; open a trader state first")]
+    TraderStateRequired,
+    #[msg("Cannot reverse a same-side trade within one slot")]
+    RoundTripBlocked,
+    #[msg("Committee needs 1-8 distinct members and a threshold within that size")]
+    InvalidCommittee,
+    #[msg("Creator already has a lookup table")]
+    LookupTableExists,
+    #[msg("Lookup table does not match the creator's")]
+    LookupTableMismatch,
+    #[msg("Instruction is paused by the protocol admin")]
+    InstructionPaused,
+    #[msg("Note URI too long")]
+    NoteUriTooLong,
+    #[msg("Resolution log is full")]
+    TooManyNotes,
+    #[msg("claim_many accounts are malformed")]
+    InvalidClaimBatch,
+    #[msg("Payout reference must be 1-64 bytes")]
+    InvalidPayoutReference,
+    #[msg("Market is not old enough to be collected")]
+    MarketNotStale,
+    #[msg("Market has traded and can't be collected")]
+    MarketHasVolume,
+    #[msg("Statement is empty or contains control characters")]
+    InvalidStatement,
+    #[msg("A battle needs two distinct agents")]
+    InvalidBattle,
+    #[msg("Market already has an outcome commitment")]
+    CommitmentExists,
+    #[msg("No outcome commitment is open for reveal")]
+    NoCommitment,
+    #[msg("Revealed outcome does not match the commitment")]
+    CommitmentMismatch,
+    #[msg("Waiting for the committed agent to reveal")]
+    AwaitingReveal,
+    #[msg("Collateral account is not owned by the payout recipient")]
+    PayoutRecipientMismatch,
+    #[msg("Template body or variables are invalid")]
+    InvalidTemplate,
+    #[msg("Question does not match the template and values")]
+    TemplateMismatch,
+    #[msg("Agent voting must stay open past the market's end time")]
+    InvalidRoundWindow,
+    #[msg("Program data account does not belong to this program")]
+    InvalidProgramData,
+    #[msg("Claims can't be held back that long")]
+    ClaimDelayTooLong,
+    #[msg("Agent-linked markets need the agent's market index accounts")]
+    AgentMarketsRequired,
+    #[msg("Agent already has its tier's maximum of open markets")]
+    TooManyOpenMarkets,
+    #[msg("Agent's keys are flagged as compromised")]
+    AgentCompromised,
+    #[msg("Proof request was made before the market closed")]
+    ProofPredatesClose,
+    #[msg("Proof request belongs to a different market")]
+    ProofMarketMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialized_len<T: AccountSerialize>(account: &T) -> usize {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data.len()
+    }
+
+    // Every variable-length field at its cap and every Option set: the
+    // largest encoding an account can reach must fit the space it's allocated
+    fn largest_market() -> Market {
+        Market {
+            authority: Pubkey::new_unique(),
+            market_id: u64::MAX,
+            agent: Some(Pubkey::new_unique()),
+            question: "q".repeat(MAX_QUESTION),
+            yes_mint: Pubkey::new_unique(),
+            no_mint: Pubkey::new_unique(),
+            collateral_mint: Pubkey::new_unique(),
+            collateral_vault: Pubkey::new_unique(),
+            allowlist_root: Some([1; 32]),
+            opens_at: 1,
+            end_time: 2,
+            resolution_mode: ResolutionMode::Committee,
+            is_resolved: true,
+            winning_outcome: Some(Outcome::No),
+            claim_enabled_at: 3,
+            transcript_hash: [2; 32],
+            transcript_uri: "u".repeat(MAX_TRANSCRIPT_URI),
+            total_yes_supply: 4,
+            total_no_supply: 5,
+            block_round_trips: true,
+            frozen: true,
+            min_trade_amount: 6,
+            outcome_commitment: Some([3; 32]),
+            list_page: 7,
+            trade_count: 8,
+            volume: 10,
+            in_lookup_table: true,
+            creator_liquidity: 9,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn market_space_fits_largest_market() {
+        assert_eq!(serialized_len(&largest_market()), 8 + Market::INIT_SPACE);
+    }
+
+    #[test]
+    fn template_space_fits_largest_template() {
+        let template = Template {
+            authority: Pubkey::new_unique(),
+            template_id: 1,
+            body: "b".repeat(MAX_QUESTION),
+            variables: vec!["v".repeat(MAX_TEMPLATE_VAR_NAME); MAX_TEMPLATE_VARS],
+            bump: 255,
+        };
+        assert_eq!(serialized_len(&template), 8 + Template::INIT_SPACE);
+    }
+
+    #[test]
+    fn translations_space_fits_every_language() {
+        let translations = Translations {
+            market: Pubkey::new_unique(),
+            entries: (0..MAX_TRANSLATIONS)
+                .map(|_| Translation {
+                    lang_code: "l".repeat(MAX_LANG_CODE),
+                    statement: "s".repeat(MAX_QUESTION),
+                })
+                .collect(),
+            bump: 255,
+        };
+        assert_eq!(serialized_len(&translations), 8 + Translations::INIT_SPACE);
+    }
+
+    #[test]
+    fn agent_markets_page_space_fits_full_page() {
+        let page = AgentMarketsPage {
+            page: 1,
+            markets: vec![Pubkey::new_unique(); MARKETS_PER_PAGE],
+            bump: 255,
+        };
+        assert_eq!(serialized_len(&page), 8 + AgentMarketsPage::INIT_SPACE);
+    }
+
+    #[test]
+    fn resolution_notes_space_tracks_length() {
+        for len in [0, 1, MAX_RESOLUTION_NOTES] {
+            let notes = ResolutionNotes {
+                market: Pubkey::new_unique(),
+                notes: vec![
+                    ResolutionNote {
+                        author: Pubkey::new_unique(),
+                        note_hash: [4; 32],
+                        uri: "n".repeat(MAX_NOTE_URI),
+                        posted_at: 1,
+                    };
+                    len
+                ],
+                bump: 255,
+            };
+            assert_eq!(serialized_len(&notes), ResolutionNotes::space(len));
+        }
+    }
+
+    #[test]
+    fn normalize_question_folds_case_and_whitespace() {
+        assert_eq!(normalize_question("  Will  BTC\thit\n100k? "), "will btc hit 100k?");
+        assert_eq!(question_hash("Will BTC hit 100k?"), question_hash("will  btc hit 100K?"));
+        assert_ne!(question_hash("Will BTC hit 100k?"), question_hash("Will BTC hit 200k?"));
+    }
+
+    #[test]
+    fn merkle_proof_verifies_either_sibling_order() {
+        let leaves: Vec<_> = (0..4u64)
+            .map(|i| airdrop_leaf(i, &Pubkey::new_unique(), 100))
+            .collect();
+        let node = |a: [u8; 32], b: [u8; 32]| {
+            let (a, b) = if a <= b { (a, b) } else { (b, a) };
+            hashv(&[&[1u8], &a, &b]).to_bytes()
+        };
+        let left = node(leaves[0], leaves[1]);
+        let right = node(leaves[2], leaves[3]);
+        let root = node(left, right);
+
+        assert!(verify_merkle_proof(&[leaves[1], right], root, leaves[0]));
+        assert!(verify_merkle_proof(&[leaves[2], left], root, leaves[3]));
+        assert!(!verify_merkle_proof(&[leaves[2], left], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[leaves[1]], root, leaves[0]));
+    }
+
+    #[test]
+    fn outcome_commitment_binds_outcome_and_salt() {
+        let salt = [7u8; 32];
+        let yes = outcome_commitment(Outcome::Yes, &salt);
+        assert_eq!(yes, outcome_commitment(Outcome::Yes, &salt));
+        assert_ne!(yes, outcome_commitment(Outcome::No, &salt));
+        assert_ne!(yes, outcome_commitment(Outcome::Yes, &[8u8; 32]));
+    }
+
+    #[test]
+    fn record_trade_blocks_same_slot_round_trip() {
+        let mut state = TraderState {
+            market: Pubkey::new_unique(),
+            trader: Pubkey::new_unique(),
+            last_buy_slot: [0; 2],
+            last_sell_slot: [0; 2],
+            bump: 255,
+        };
+        state.record_trade(10, Outcome::Yes, true).unwrap();
+        // Same side, same direction and the other side are all fine
+        state.record_trade(10, Outcome::Yes, true).unwrap();
+        state.record_trade(10, Outcome::No, false).unwrap();
+        assert_eq!(
+            state.record_trade(10, Outcome::Yes, false).unwrap_err(),
+            ErrorCode::RoundTripBlocked.into()
+        );
+        assert_eq!(
+            state.record_trade(10, Outcome::No, true).unwrap_err(),
+            ErrorCode::RoundTripBlocked.into()
+        );
+        // Reversing in a later slot is allowed
+        state.record_trade(11, Outcome::Yes, false).unwrap();
+        assert_eq!(state.last_buy_slot, [10, 0]);
+        assert_eq!(state.last_sell_slot, [11, 10]);
+    }
+
+    #[test]
+    fn render_template_fills_declared_placeholders() {
+        let names = vec!["agent_a".to_string(), "n".to_string()];
+        let values = vec!["alice".to_string(), "3".to_string()];
+        assert_eq!(
+            render_template("Will {agent_a} win round {n}?", &names, &values).as_deref(),
+            Some("Will alice win round 3?")
+        );
+        assert_eq!(render_template("Round {m}?", &names, &values), None);
+        assert_eq!(render_template("Round {n?", &names, &values), None);
+        assert_eq!(render_template("Round {n}", &names, &values[..1]), None);
+    }
+
+    #[test]
+    fn render_template_rejects_stray_close_brace() {
+        let names = vec!["n".to_string()];
+        let values = vec!["3".to_string()];
+        assert_eq!(render_template("Round} {n}?", &names, &values), None);
+        assert_eq!(render_template("Round {n}} ?", &names, &values), None);
+        assert_eq!(render_template("Round {n}?}", &names, &values), None);
+        assert_eq!(render_template("Round }", &names, &values), None);
+    }
+
+    #[test]
+    fn statement_rejects_control_and_format_chars() {
+        assert!(is_valid_statement("Will BTC hit 100k?"));
+        assert!(is_valid_statement("Vai o BTC chegar a 100 mil? \u{1F680}"));
+        assert!(!is_valid_statement(" \t "));
+        for c in ['\u{0007}', '\u{00AD}', '\u{180E}', '\u{200B}', '\u{202E}', '\u{2060}', '\u{FEFF}', '\u{E0041}'] {
+            assert!(!is_valid_statement(&format!("Will BTC{c} hit 100k?")), "{:X}", c as u32);
+        }
+    }
+
+    #[test]
+    fn format_char_table_is_sorted_and_disjoint() {
+        assert!(FORMAT_CHARS.iter().all(|&(lo, hi)| lo <= hi));
+        assert!(FORMAT_CHARS.windows(2).all(|w| w[0].1 < w[1].0));
+    }
+
+    #[test]
+    fn resolve_from_requires_matching_mode_and_proof() {
+        let mut request = ProofRequest {
+            agent: Pubkey::new_unique(),
+            requester: Pubkey::new_unique(),
+            market_id: [0; 32],
+            requested_at: 0,
+            deadline_ts: 0,
+            proof_uri: String::new(),
+            log_root: [0; 32],
+            signature: [0; 64],
+            signer: Pubkey::default(),
+            fulfilled: true,
+            slashable: false,
+            subscription_open: false,
+            deposit: 0,
+            bump: 255,
+        };
+        let mut market = largest_market();
+        market.is_resolved = false;
+        market.winning_outcome = None;
+        market.resolution_mode = ResolutionMode::AgentProof;
+
+        assert_eq!(
+            market.resolve_from(&AuthorityDecision(Outcome::Yes), 0, 0).err(),
+            Some(ErrorCode::WrongResolutionMode.into())
+        );
+        // Slashed: fulfilled, but no proof behind it
+        let slashed = AgentProof {
+            request: &request,
+            outcome: Outcome::Yes,
+        };
+        assert_eq!(
+            market.resolve_from(&slashed, 0, 0).err(),
+            Some(ErrorCode::RequestNotFulfilled.into())
+        );
+        assert!(!market.is_resolved);
+
+        request.proof_uri = "ipfs://proof".to_string();
+        let proven = AgentProof {
+            request: &request,
+            outcome: Outcome::No,
+        };
+        assert!(market.resolve_from(&proven, 0, 0).unwrap() == Outcome::No);
+        assert!(market.is_resolved && market.winning_outcome == Some(Outcome::No));
+    }
+
+    #[test]
+    fn settle_against_lopsided_supply_delays_claims() {
+        let mut market = largest_market();
+        market.total_yes_supply = 95;
+        market.total_no_supply = 5;
+
+        market.settle(Outcome::Yes, 0, 100).unwrap();
+        assert_eq!(market.claim_enabled_at, 0);
+        market.settle(Outcome::No, 0, 100).unwrap();
+        assert_eq!(market.claim_enabled_at, 100 + CONTESTED_DISPUTE_SECS);
+        // A later requested delay is kept
+        market.settle(Outcome::No, i64::MAX, 100).unwrap();
+        assert_eq!(market.claim_enabled_at, i64::MAX);
+    }
+}
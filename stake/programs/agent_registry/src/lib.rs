@@ -1,14 +1,58 @@
+// Only for anchor 0.31's generated IDL resize handler, which still calls
+// AccountInfo::realloc (deprecated in solana-account-info 2.3). `#[program]`
+// emits it into a `__private` module at the crate root, even with `no-idl`, so
+// no narrower item can carry the allow. Drop this with the anchor upgrade that
+// switches the handler to `resize`; nothing in this file calls a deprecated API.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::solana_program::hash::hash;
 use anchor_lang::solana_program::sysvar::Sysvar;
 
+#[cfg(all(feature = "devnet", feature = "mainnet"))]
+compile_error!("features `devnet` and `mainnet` are mutually exclusive");
+
+// Localnet and devnet share the devnet deploy key (see Anchor.toml).
+#[cfg(not(feature = "mainnet"))]
 declare_id!("CQZEo9zd8QNgT2uUJRn1cdHxc2794xFumQu9ZXL4Syk8");
+// Mainnet reuses the same deploy key until a dedicated one is generated;
+// swap it here when that happens.
+#[cfg(feature = "mainnet")]
+declare_id!("CQZEo9zd8QNgT2uUJRn1cdHxc2794xFumQu9ZXL4Syk8");
+
+// Per-cluster parameter bounds enforced by initialize_registry
+#[cfg(not(feature = "mainnet"))]
+mod cluster {
+    pub const MIN_BOND_LAMPORTS: u64 = 10_000_000; // 0.01 SOL
+    pub const MAX_BOND_LAMPORTS: u64 = 10_000_000_000; // 10 SOL
+}
+
+#[cfg(feature = "mainnet")]
+mod cluster {
+    pub const MIN_BOND_LAMPORTS: u64 = 1_000_000_000; // 1 SOL
+    pub const MAX_BOND_LAMPORTS: u64 = 1_000_000_000_000; // 1000 SOL
+}
+
+const _: () = assert!(cluster::MIN_BOND_LAMPORTS > 0);
+const _: () = assert!(cluster::MIN_BOND_LAMPORTS <= cluster::MAX_BOND_LAMPORTS);
 
 // Seeds
 const REGISTRY_SEED: &[u8] = b"registry";
 const AGENT_SEED: &[u8] = b"agent";
 const VAULT_SEED: &[u8] = b"vault";
 const REQUEST_SEED: &[u8] = b"request";
+const ROUND_SEED: &[u8] = b"round";
+const VOTE_SEED: &[u8] = b"vote";
+const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+const PENDING_SLASH_SEED: &[u8] = b"pending_slash";
+const REQUESTER_SEED: &[u8] = b"requester";
+const EARNINGS_SEED: &[u8] = b"earnings";
+const TAG_SEED: &[u8] = b"tag";
+
+const DEFAULT_APPEAL_WINDOW_SECS: i64 = 24 * 60 * 60;
+// Votes a resolution round needs before its heavier side counts as an outcome
+const DEFAULT_ROUND_QUORUM: u32 = 3;
 
 // Max lengths to keep accounts bounded
 const MAX_NAME: usize = 32;
@@ -16,6 +60,9 @@ const MAX_URL: usize = 128;
 const MAX_TAGS: usize = 8;
 const MAX_TAG_LEN: usize = 24;
 const MAX_PROOF_URI: usize = 256;
+const MAX_ATTESTATION_URI: usize = 256;
+const MAX_SIGNING_KEYS: usize = 4;
+const MAX_TAG_INDEX_AGENTS: usize = 64;
 
 #[program]
 pub mod agent_registry {
@@ -26,21 +73,40 @@ pub mod agent_registry {
         bond_lamports: u64,
         slash_penalty_lamports: u64,
     ) -> Result<()> {
+        require!(
+            (cluster::MIN_BOND_LAMPORTS..=cluster::MAX_BOND_LAMPORTS).contains(&bond_lamports),
+            AgentRegistryError::BondOutOfRange
+        );
         let registry = &mut ctx.accounts.registry;
         registry.authority = ctx.accounts.authority.key();
         registry.bond_lamports = bond_lamports;
         registry.slash_penalty_lamports = slash_penalty_lamports.min(bond_lamports);
+        registry.expected_upgrade_authority = None;
+        registry.appeal_window_secs = DEFAULT_APPEAL_WINDOW_SECS;
+        registry.bond_version = 0;
+        registry.request_cooldown_secs = 0;
+        registry.request_deposit_lamports = 0;
+        registry.round_quorum = DEFAULT_ROUND_QUORUM;
+        // Higher tiers start at multiples of the base bond; tunable via configure_tier
+        registry.tiers = [
+            TierConfig { bond_lamports, max_open_markets: 3 },
+            TierConfig { bond_lamports: bond_lamports.saturating_mul(5), max_open_markets: 10 },
+            TierConfig { bond_lamports: bond_lamports.saturating_mul(20), max_open_markets: 50 },
+        ];
         registry.bump = ctx.bumps.registry;
         Ok(())
     }
 
-    pub fn register_agent(
-        ctx: Context<RegisterAgent>,
+    /// The tag index of each of `tags` is passed as a remaining account, in
+    /// tag order.
+    pub fn register_agent<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RegisterAgent<'info>>,
         name: String,
         url: String,
         tags: Vec<String>,
     ) -> Result<()> {
         validate_metadata(&name, &url, &tags)?;
+        sync_tag_indexes(ctx.remaining_accounts, ctx.accounts.agent.key(), &[], &tags)?;
 
         let registry = &ctx.accounts.registry;
         let payer = &ctx.accounts.payer;
@@ -53,64 +119,132 @@ pub mod agent_registry {
         agent.url = url;
         agent.tags = tags;
         agent.bond_lamports = registry.bond_lamports;
+        agent.tier = AgentTier::Bronze;
+        agent.bond_version = registry.bond_version;
         agent.request_count = 0;
+        agent.missed_requests = 0;
         agent.pending_request = None;
+        agent.model_hash = [0u8; 32];
+        agent.attestation_uri = String::new();
+        agent.compromised = false;
+        agent.signing_keys = Vec::new();
+        agent.open_votes = 0;
+        agent.open_subscription_requests = 0;
         agent.bump = ctx.bumps.agent;
 
-        // Verify and initialize vault PDA
         let agent_key = agent.key();
-        let (expected_vault, vault_bump) = Pubkey::find_program_address(
-            &[VAULT_SEED, agent_key.as_ref()],
-            ctx.program_id,
-        );
-        require_keys_eq!(ctx.accounts.vault.key(), expected_vault, AgentRegistryError::InvalidRequest);
-        
-        // Initialize vault if needed (create account with rent exemption)
-        let vault_lamports = ctx.accounts.vault.to_account_info().lamports();
-        let rent = Rent::get()?;
-        let required_lamports = rent.minimum_balance(8);
-        if vault_lamports < required_lamports {
-            let create_account_ix = anchor_lang::solana_program::system_instruction::create_account(
-                &payer.key(),
-                &expected_vault,
-                required_lamports,
-                8,
-                &ctx.program_id,
-            );
-            anchor_lang::solana_program::program::invoke_signed(
-                &create_account_ix,
-                &[
-                    payer.to_account_info(),
-                    ctx.accounts.vault.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-                &[&[VAULT_SEED, agent_key.as_ref(), &[vault_bump]]],
-            )?;
-        }
+
+        // The vault stays system-owned so slashes and withdrawals can move
+        // lamports out with a PDA-signed system transfer. Fund its rent
+        // reserve on top of the bond so partial slashes never strand it.
+        let vault_lamports = ctx.accounts.vault.lamports();
+        let rent_reserve = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(vault_lamports);
+        let deposit = registry
+            .bond_lamports
+            .checked_add(rent_reserve)
+            .ok_or(AgentRegistryError::Overflow)?;
 
         // Transfer bond into escrow vault PDA
         transfer(
-            CpiContext::new_with_signer(
+            CpiContext::new(
                 ctx.accounts.system_program.to_account_info(),
                 Transfer {
                     from: payer.to_account_info(),
                     to: ctx.accounts.vault.to_account_info(),
                 },
-                &[&[VAULT_SEED, agent_key.as_ref(), &[vault_bump]]],
             ),
-            registry.bond_lamports,
+            deposit,
         )?;
 
+        emit!(AgentRegistered {
+            agent: agent_key,
+            authority: agent.authority,
+            agent_wallet: agent.agent_wallet,
+            name: agent.name.clone(),
+            url: agent.url.clone(),
+            tags: agent.tags.clone(),
+            bond_lamports: agent.bond_lamports,
+        });
+
         Ok(())
     }
 
-    pub fn update_metadata(ctx: Context<UpdateMetadata>, name: String, url: String, tags: Vec<String>) -> Result<()> {
+    /// Remaining accounts are the tag indexes of the dropped tags, then those
+    /// of the added tags, each in tag order.
+    pub fn update_metadata<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateMetadata<'info>>,
+        name: String,
+        url: String,
+        tags: Vec<String>,
+    ) -> Result<()> {
         validate_metadata(&name, &url, &tags)?;
         let agent = &mut ctx.accounts.agent;
-        require_keys_eq!(agent.authority, ctx.accounts.authority.key(), AgentRegistryError::Unauthorized);
+        sync_tag_indexes(ctx.remaining_accounts, agent.key(), &agent.tags, &tags)?;
         agent.name = name;
         agent.url = url;
         agent.tags = tags;
+
+        emit!(AgentMetadataUpdated {
+            agent: agent.key(),
+            name: agent.name.clone(),
+            url: agent.url.clone(),
+            tags: agent.tags.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Create the discovery index for `tag`. Anyone may pay for it; it must
+    /// exist before an agent can register under the tag.
+    pub fn init_tag_index(ctx: Context<InitTagIndex>, tag: String) -> Result<()> {
+        require!(!tag.is_empty() && tag.len() <= MAX_TAG_LEN, AgentRegistryError::TagTooLong);
+        let index = &mut ctx.accounts.tag_index;
+        index.tag = tag;
+        index.agents = Vec::new();
+        index.bump = ctx.bumps.tag_index;
+        Ok(())
+    }
+
+    /// Register the keys that may sign for the agent alongside agent_wallet.
+    /// Needed when agent_wallet is a PDA (e.g. a DAO-run agent) and so can't
+    /// sign a transaction itself. Replaces the whole list.
+    pub fn set_signing_keys(ctx: Context<SetSigningKeys>, signing_keys: Vec<Pubkey>) -> Result<()> {
+        require!(signing_keys.len() <= MAX_SIGNING_KEYS, AgentRegistryError::TooManySigningKeys);
+        let agent = &mut ctx.accounts.agent;
+        agent.signing_keys = signing_keys;
+
+        emit!(SigningKeysSet {
+            agent: agent.key(),
+            signing_keys: agent.signing_keys.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Record which model build the agent runs. Signed by the agent wallet
+    /// (or one of its signing keys), the key that actually operates the
+    /// model, not the registering authority.
+    pub fn attest_model(
+        ctx: Context<AttestModel>,
+        model_hash: [u8; 32],
+        attestation_uri: String,
+    ) -> Result<()> {
+        require!(
+            attestation_uri.len() <= MAX_ATTESTATION_URI,
+            AgentRegistryError::AttestationUriTooLong
+        );
+        let agent = &mut ctx.accounts.agent;
+        agent.model_hash = model_hash;
+        agent.attestation_uri = attestation_uri;
+
+        emit!(ModelAttested {
+            agent: agent.key(),
+            model_hash,
+            attestation_uri: agent.attestation_uri.clone(),
+        });
+
         Ok(())
     }
 
@@ -118,15 +252,49 @@ pub mod agent_registry {
         require_gte!(deadline_ts, Clock::get()?.unix_timestamp, AgentRegistryError::DeadlineInPast);
     let agent = &mut ctx.accounts.agent;
     require!(agent.pending_request.is_none(), AgentRegistryError::ActiveRequestPresent);
+        require!(agent.is_bonded(&ctx.accounts.registry), AgentRegistryError::UnderBonded);
+        require!(!agent.compromised, AgentRegistryError::AgentCompromised);
+
+        let now = Clock::get()?.unix_timestamp;
+        let registry = &ctx.accounts.registry;
+        let requester_state = &mut ctx.accounts.requester_state;
+        // A fresh state has last_request_ts == 0, which never trips the cooldown
+        if requester_state.last_request_ts != 0 {
+            let ready_at = requester_state
+                .last_request_ts
+                .checked_add(registry.request_cooldown_secs)
+                .ok_or(AgentRegistryError::Overflow)?;
+            require!(now >= ready_at, AgentRegistryError::RequestCooldown);
+        }
+        requester_state.last_request_ts = now;
+        requester_state.bump = ctx.bumps.requester_state;
+
+        // Held on the request account itself; closing the request refunds it
+        let deposit = registry.request_deposit_lamports;
+        if deposit > 0 {
+            transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.requester.to_account_info(),
+                        to: ctx.accounts.proof_request.to_account_info(),
+                    },
+                ),
+                deposit,
+            )?;
+        }
 
         let request = &mut ctx.accounts.proof_request;
         request.agent = agent.key();
         request.market_id = market_id;
         request.requester = ctx.accounts.requester.key();
-        request.requested_at = Clock::get()?.unix_timestamp;
+        request.requested_at = now;
+        request.deposit = deposit;
+        request.signer = Pubkey::default();
         request.deadline_ts = deadline_ts;
         request.fulfilled = false;
         request.slashable = true;
+        request.subscription_open = false;
         request.proof_uri = String::new();
         request.log_root = [0u8; 32];
         request.bump = ctx.bumps.proof_request;
@@ -154,42 +322,104 @@ pub mod agent_registry {
         require!(proof_uri.len() <= MAX_PROOF_URI, AgentRegistryError::ProofUriTooLong);
         let request = &mut ctx.accounts.proof_request;
         require!(!request.fulfilled, AgentRegistryError::RequestAlreadyFulfilled);
-        require_keys_eq!(request.agent, ctx.accounts.agent.key(), AgentRegistryError::InvalidRequest);
         require!(request.market_id == market_id, AgentRegistryError::InvalidRequest);
 
         // Only agent authority or designated wallet may submit
         let agent = &mut ctx.accounts.agent;
-        require_keys_eq!(agent.authority, ctx.accounts.authority.key(), AgentRegistryError::Unauthorized);
 
         request.proof_uri = proof_uri;
         request.log_root = log_root;
         request.signature = signature;
+        request.signer = ctx.accounts.submitter.key();
         request.fulfilled = true;
         request.slashable = false;
-        agent.pending_request = None;
+        // Subscription requests never occupy the agent's pending slot
+        if agent.pending_request == Some(request.key()) {
+            agent.pending_request = None;
+        }
+        agent.settle_subscription_request(request);
 
         emit!(ProofSubmitted {
             agent: agent.key(),
             market_id,
             request: request.key(),
             proof_uri: request.proof_uri.clone(),
-            log_root
+            log_root,
+            model_hash: agent.model_hash,
+            signer: request.signer,
         });
 
         Ok(())
     }
 
-    pub fn slash_agent(ctx: Context<SlashAgent>) -> Result<()> {
+    /// First step of a slash: open an appeal window on a missed request.
+    /// Funds stay in the vault until finalize_slash.
+    pub fn mark_slash(ctx: Context<MarkSlash>) -> Result<()> {
         let registry = &ctx.accounts.registry;
-        let request = &mut ctx.accounts.proof_request;
+        let request = &ctx.accounts.proof_request;
         require!(request.slashable, AgentRegistryError::NotSlashable);
         require!(!request.fulfilled, AgentRegistryError::RequestAlreadyFulfilled);
+        let now = Clock::get()?.unix_timestamp;
+        require!(now > request.deadline_ts, AgentRegistryError::DeadlineNotReached);
+
+        let pending = &mut ctx.accounts.pending_slash;
+        pending.agent = ctx.accounts.agent.key();
+        pending.request = request.key();
+        pending.penalty = registry.slash_penalty_lamports;
+        pending.appeal_ends_ts = now
+            .checked_add(registry.appeal_window_secs)
+            .ok_or(AgentRegistryError::Overflow)?;
+        pending.bump = ctx.bumps.pending_slash;
+
+        emit!(SlashMarked {
+            agent: pending.agent,
+            request: pending.request,
+            penalty: pending.penalty,
+            appeal_ends_ts: pending.appeal_ends_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Registry authority cancels a pending slash; the request can no longer be slashed.
+    pub fn veto_slash(ctx: Context<VetoSlash>) -> Result<()> {
+        let request = &mut ctx.accounts.proof_request;
+        request.slashable = false;
+        ctx.accounts.agent.settle_subscription_request(request);
+
+        emit!(SlashVetoed {
+            agent: ctx.accounts.pending_slash.agent,
+            request: ctx.accounts.pending_slash.request,
+        });
+
+        Ok(())
+    }
+
+    /// Second step: once the appeal window has passed, move the penalty out of
+    /// the vault. A proof submitted during the window voids the slash instead.
+    pub fn finalize_slash(ctx: Context<FinalizeSlash>) -> Result<()> {
+        let pending = &ctx.accounts.pending_slash;
         require!(
-            Clock::get()?.unix_timestamp > request.deadline_ts,
-            AgentRegistryError::DeadlineNotReached
+            Clock::get()?.unix_timestamp > pending.appeal_ends_ts,
+            AgentRegistryError::AppealWindowOpen
         );
 
+        let request = &mut ctx.accounts.proof_request;
+        if request.fulfilled {
+            emit!(SlashVetoed {
+                agent: pending.agent,
+                request: pending.request,
+            });
+            return Ok(());
+        }
+
         let vault = &mut ctx.accounts.vault;
+        // Same cap as slash_dissenter: the recorded bond, above the vault's rent reserve
+        let rent_reserve = Rent::get()?.minimum_balance(0);
+        let penalty = pending
+            .penalty
+            .min(ctx.accounts.agent.bond_lamports)
+            .min(vault.lamports().saturating_sub(rent_reserve));
 
         // Transfer slash penalty to authority
         let bump = ctx.bumps.vault;
@@ -200,7 +430,7 @@ pub mod agent_registry {
             &[bump],
         ];
         let signer = &[vault_seeds];
-        let ix = transfer(
+        transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.system_program.to_account_info(),
                 Transfer {
@@ -209,29 +439,129 @@ pub mod agent_registry {
                 },
                 signer,
             ),
-            registry.slash_penalty_lamports,
-        );
-        ix?;
+            penalty,
+        )?;
+        let agent = &mut ctx.accounts.agent;
+        agent.bond_lamports = agent
+            .bond_lamports
+            .checked_sub(penalty)
+            .ok_or(AgentRegistryError::Overflow)?;
 
         // Mark request as resolved to prevent double slashing
         request.slashable = false;
         request.fulfilled = true;
-        ctx.accounts.agent.pending_request = None;
+        if agent.pending_request == Some(request.key()) {
+            agent.pending_request = None;
+        }
+        agent.settle_subscription_request(request);
 
         emit!(AgentSlashed {
-            agent: ctx.accounts.agent.key(),
+            agent: agent.key(),
+            request: request.key(),
+            market_id: request.market_id,
+            penalty,
+        });
+
+        Ok(())
+    }
+
+    /// Close a missed request nobody slashed, refunding its rent to the
+    /// requester. Only possible once the registry authority has had a full
+    /// appeal window's worth of time to mark it. The miss always counts
+    /// against the agent's reliability: by now the deadline has passed with
+    /// no proof, and the authority neither slashed nor vetoed it.
+    pub fn expire_request(ctx: Context<ExpireRequest>) -> Result<()> {
+        let request = &mut ctx.accounts.proof_request;
+        require!(!request.fulfilled, AgentRegistryError::RequestAlreadyFulfilled);
+        require!(request.slashable, AgentRegistryError::NotSlashable);
+        let expires_at = request
+            .deadline_ts
+            .checked_add(ctx.accounts.registry.appeal_window_secs)
+            .ok_or(AgentRegistryError::Overflow)?;
+        require!(
+            Clock::get()?.unix_timestamp > expires_at,
+            AgentRegistryError::DeadlineNotReached
+        );
+
+        let agent = &mut ctx.accounts.agent;
+        if agent.pending_request == Some(request.key()) {
+            agent.pending_request = None;
+        }
+        agent.settle_subscription_request(request);
+        agent.missed_requests = agent.missed_requests.checked_add(1).ok_or(AgentRegistryError::Overflow)?;
+
+        emit!(RequestExpired {
+            agent: agent.key(),
             request: request.key(),
             market_id: request.market_id,
-            penalty: registry.slash_penalty_lamports,
+            requester: request.requester,
+        });
+
+        Ok(())
+    }
+
+    /// Spam controls for request_proof: the minimum gap between two requests
+    /// from one requester, and a deposit refunded when the request is closed.
+    pub fn set_request_limits(
+        ctx: Context<SetRequestLimits>,
+        request_cooldown_secs: i64,
+        request_deposit_lamports: u64,
+    ) -> Result<()> {
+        require_gte!(request_cooldown_secs, 0, AgentRegistryError::InvalidCooldown);
+        let registry = &mut ctx.accounts.registry;
+        registry.request_cooldown_secs = request_cooldown_secs;
+        registry.request_deposit_lamports = request_deposit_lamports;
+        Ok(())
+    }
+
+    /// Close a settled direct request (proof submitted, slash finalized or
+    /// vetoed), returning its rent and deposit to the requester and freeing
+    /// the agent's request slot.
+    pub fn close_request(ctx: Context<CloseRequest>) -> Result<()> {
+        let request = &ctx.accounts.proof_request;
+        require!(
+            request.fulfilled || !request.slashable,
+            AgentRegistryError::RequestNotSettled
+        );
+
+        let agent = &mut ctx.accounts.agent;
+        if agent.pending_request == Some(request.key()) {
+            agent.pending_request = None;
+        }
+
+        emit!(RequestClosed {
+            agent: agent.key(),
+            request: request.key(),
+            requester: request.requester,
+            deposit: request.deposit,
         });
 
         Ok(())
     }
 
+    pub fn set_appeal_window(ctx: Context<SetAppealWindow>, appeal_window_secs: i64) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        require_gte!(appeal_window_secs, 0, AgentRegistryError::InvalidAppealWindow);
+        registry.appeal_window_secs = appeal_window_secs;
+        Ok(())
+    }
+
+    /// Minimum number of votes a resolution round needs to produce an outcome.
+    pub fn set_round_quorum(ctx: Context<SetRoundQuorum>, round_quorum: u32) -> Result<()> {
+        require_gte!(round_quorum, 1, AgentRegistryError::InvalidQuorum);
+        ctx.accounts.registry.round_quorum = round_quorum;
+        Ok(())
+    }
+
     pub fn withdraw_bond(ctx: Context<WithdrawBond>) -> Result<()> {
-        let agent = &ctx.accounts.agent;
-        require_keys_eq!(agent.authority, ctx.accounts.authority.key(), AgentRegistryError::Unauthorized);
+        let agent = &mut ctx.accounts.agent;
         require!(agent.pending_request.is_none(), AgentRegistryError::ActiveRequestPresent);
+        // The bond backs every vote until its round settles
+        require!(agent.open_votes == 0, AgentRegistryError::VotePending);
+        require!(
+            agent.open_subscription_requests == 0,
+            AgentRegistryError::SubscriptionRequestsOutstanding
+        );
 
         let vault = &mut ctx.accounts.vault;
         let lamports = vault.to_account_info().lamports();
@@ -256,186 +586,1358 @@ pub mod agent_registry {
             ),
             lamports,
         )?;
+        agent.bond_lamports = 0;
+
+        emit!(BondWithdrawn {
+            agent: agent_key,
+            authority: agent.authority,
+            lamports,
+        });
 
         Ok(())
     }
-}
 
-fn validate_metadata(name: &str, url: &str, tags: &[String]) -> Result<()> {
-    require!(!name.is_empty() && name.len() <= MAX_NAME, AgentRegistryError::NameTooLong);
-    require!(!url.is_empty() && url.len() <= MAX_URL, AgentRegistryError::UrlTooLong);
-    require!(tags.len() <= MAX_TAGS, AgentRegistryError::TooManyTags);
-    for t in tags {
-        require!(!t.is_empty() && t.len() <= MAX_TAG_LEN, AgentRegistryError::TagTooLong);
+    /// Remove an agent whose bond has been withdrawn; the account's rent
+    /// returns to its authority. The tag indexes of its tags are passed as
+    /// remaining accounts so it drops out of discovery.
+    pub fn deregister_agent<'info>(ctx: Context<'_, '_, 'info, 'info, DeregisterAgent<'info>>) -> Result<()> {
+        let agent = &ctx.accounts.agent;
+        require!(agent.pending_request.is_none(), AgentRegistryError::ActiveRequestPresent);
+        require!(
+            agent.open_subscription_requests == 0,
+            AgentRegistryError::SubscriptionRequestsOutstanding
+        );
+        require!(agent.bond_lamports == 0, AgentRegistryError::BondNotWithdrawn);
+        sync_tag_indexes(ctx.remaining_accounts, agent.key(), &agent.tags, &[])?;
+
+        emit!(AgentDeregistered {
+            agent: agent.key(),
+            authority: agent.authority,
+            agent_wallet: agent.agent_wallet,
+        });
+
+        Ok(())
     }
-    Ok(())
-}
 
-// Accounts
-#[derive(Accounts)]
-pub struct InitializeRegistry<'info> {
-    #[account(init, payer = authority, seeds = [REGISTRY_SEED], bump, space = 8 + Registry::LEN)]
-    pub registry: Account<'info, Registry>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Mark an agent's keys as leaked. Either the registry or the agent's own
+    /// authority may raise the flag; markets referencing the agent can then
+    /// be frozen and it stops taking proof requests.
+    pub fn flag_compromised(ctx: Context<FlagCompromised>) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        agent.compromised = true;
 
-#[derive(Accounts)]
-pub struct RegisterAgent<'info> {
-    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump)]
-    pub registry: Account<'info, Registry>,
-    #[account(
-        init,
-        payer = payer,
-        seeds = [AGENT_SEED, agent_wallet.key().as_ref()],
-        bump,
-        space = 8 + Agent::LEN
-    )]
-    pub agent: Account<'info, Agent>,
-    /// CHECK: Agent wants funds to flow to this wallet; doesn't need to be signer.
-    pub agent_wallet: UncheckedAccount<'info>,
-    /// CHECK: Vault PDA for agent bond escrow
-    #[account(mut)]
-    pub vault: UncheckedAccount<'info>,
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        emit!(AgentCompromised {
+            agent: agent.key(),
+            flagged_by: ctx.accounts.signer.key(),
+        });
 
-#[derive(Accounts)]
-pub struct UpdateMetadata<'info> {
-    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
-    pub agent: Account<'info, Agent>,
-    pub authority: Signer<'info>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct RequestProof<'info> {
-    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
-    pub agent: Account<'info, Agent>,
-    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
-    pub registry: Account<'info, Registry>,
-    #[account(
-        init,
-        payer = requester,
-        seeds = [REQUEST_SEED, agent.key().as_ref()],
-        bump,
-        space = 8 + ProofRequest::LEN
-    )]
-    pub proof_request: Account<'info, ProofRequest>,
-    #[account(mut)]
-    pub requester: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    pub fn configure_tier(
+        ctx: Context<ConfigureTier>,
+        tier: AgentTier,
+        bond_lamports: u64,
+        max_open_markets: u16,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        if registry.tier(tier).bond_lamports != bond_lamports {
+            registry.bond_version = registry.bond_version.checked_add(1).ok_or(AgentRegistryError::Overflow)?;
+            emit!(BondRequirementChanged {
+                tier,
+                bond_lamports,
+                bond_version: registry.bond_version,
+            });
+        }
+        // Bronze is the entry tier, so its bond is what new agents post
+        if tier == AgentTier::Bronze {
+            registry.bond_lamports = bond_lamports;
+            registry.slash_penalty_lamports = registry.slash_penalty_lamports.min(bond_lamports);
+        }
+        registry.tiers[tier as usize] = TierConfig { bond_lamports, max_open_markets };
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct SubmitProof<'info> {
-    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
-    pub agent: Account<'info, Agent>,
-    #[account(mut, seeds = [REQUEST_SEED, agent.key().as_ref()], bump = proof_request.bump)]
-    pub proof_request: Account<'info, ProofRequest>,
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Add lamports to an agent's bond, e.g. after the registry raised the
+    /// requirement or the agent was promoted.
+    pub fn top_up_bond(ctx: Context<TopUpBond>, amount: u64) -> Result<()> {
+        require!(amount > 0, AgentRegistryError::InvalidBondAmount);
+        let agent = &mut ctx.accounts.agent;
 
-#[derive(Accounts)]
-pub struct SlashAgent<'info> {
-    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
-    pub registry: Account<'info, Registry>,
-    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
-    pub agent: Account<'info, Agent>,
-    #[account(
-        mut,
-        seeds = [REQUEST_SEED, agent.key().as_ref()],
-        bump = proof_request.bump
-    )]
-    pub proof_request: Account<'info, ProofRequest>,
-    #[account(
-        mut,
-        seeds = [VAULT_SEED, agent.key().as_ref()],
-        bump
-    )]
-    pub vault: SystemAccount<'info>,
-    /// Registry authority receives penalties
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
 
-#[derive(Accounts)]
-pub struct WithdrawBond<'info> {
-    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+        agent.bond_lamports = agent.bond_lamports.checked_add(amount).ok_or(AgentRegistryError::Overflow)?;
+        let registry = &ctx.accounts.registry;
+        if agent.is_bonded(registry) {
+            agent.bond_version = registry.bond_version;
+        }
+
+        emit!(BondToppedUp {
+            agent: agent.key(),
+            amount,
+            bond_lamports: agent.bond_lamports,
+            bond_version: agent.bond_version,
+        });
+
+        Ok(())
+    }
+
+    /// Pay lamports into an agent's earnings account. Used for request fees,
+    /// prize shares and market-creation rebates; `source` only drives the
+    /// running totals.
+    pub fn credit_earnings(ctx: Context<CreditEarnings>, source: EarningsSource, amount: u64) -> Result<()> {
+        require!(amount > 0, AgentRegistryError::InvalidEarningsAmount);
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.earnings.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let earnings = &mut ctx.accounts.earnings;
+        earnings.agent = ctx.accounts.agent.key();
+        earnings.bump = ctx.bumps.earnings;
+        let total = match source {
+            EarningsSource::RequestFee => &mut earnings.request_fees,
+            EarningsSource::PrizeShare => &mut earnings.prize_shares,
+            EarningsSource::CreationRebate => &mut earnings.creation_rebates,
+        };
+        *total = total.checked_add(amount).ok_or(AgentRegistryError::Overflow)?;
+
+        emit!(EarningsCredited {
+            agent: earnings.agent,
+            source,
+            amount,
+            payer: ctx.accounts.payer.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Sweep everything above rent from the earnings account to agent_wallet.
+    pub fn claim_earnings(ctx: Context<ClaimEarnings>) -> Result<()> {
+        let earnings = &mut ctx.accounts.earnings;
+        let rent = Rent::get()?.minimum_balance(earnings.to_account_info().data_len());
+        let amount = earnings.to_account_info().lamports().saturating_sub(rent);
+        require!(amount > 0, AgentRegistryError::NothingToClaim);
+
+        earnings.sub_lamports(amount)?;
+        ctx.accounts.agent_wallet.add_lamports(amount)?;
+        earnings.claimed = earnings.claimed.checked_add(amount).ok_or(AgentRegistryError::Overflow)?;
+
+        emit!(EarningsClaimed {
+            agent: earnings.agent,
+            agent_wallet: ctx.accounts.agent_wallet.key(),
+            amount,
+            total_claimed: earnings.claimed,
+        });
+
+        Ok(())
+    }
+
+    /// Move an agent to another tier (promotion or demotion). The agent must
+    /// cover the new tier's bond before it can take further proof requests.
+    pub fn set_agent_tier(ctx: Context<SetAgentTier>, tier: AgentTier) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        let previous = agent.tier;
+        agent.tier = tier;
+
+        emit!(AgentTierChanged {
+            agent: agent.key(),
+            previous,
+            tier,
+            required_bond_lamports: ctx.accounts.registry.tier(tier).bond_lamports,
+        });
+
+        Ok(())
+    }
+
+    /// Fund a standing subscription: one proof request for `market_id` every
+    /// `interval_secs`, starting at `start_ts`, for `periods` periods. The
+    /// requester prepays the rent of every request the crank will create.
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        market_id: [u8; 32],
+        start_ts: i64,
+        interval_secs: i64,
+        periods: u32,
+    ) -> Result<()> {
+        require_gte!(start_ts, Clock::get()?.unix_timestamp, AgentRegistryError::DeadlineInPast);
+        require!(interval_secs > 0 && periods > 0, AgentRegistryError::InvalidSubscription);
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.agent = ctx.accounts.agent.key();
+        subscription.requester = ctx.accounts.requester.key();
+        subscription.market_id = market_id;
+        subscription.start_ts = start_ts;
+        subscription.interval_secs = interval_secs;
+        subscription.periods = periods;
+        subscription.materialized = 0;
+        subscription.bump = ctx.bumps.subscription;
+
+        let request_rent = Rent::get()?.minimum_balance(8 + ProofRequest::INIT_SPACE);
+        let prepaid = request_rent
+            .checked_mul(periods as u64)
+            .ok_or(AgentRegistryError::Overflow)?;
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.requester.to_account_info(),
+                    to: subscription.to_account_info(),
+                },
+            ),
+            prepaid,
+        )?;
+
+        Ok(())
+    }
+
+    /// Permissionless crank creating the next period's proof request once
+    /// that period has started. The crank is reimbursed the request's rent
+    /// from the subscription; a missed deadline is slashable via mark_slash.
+    pub fn crank_subscription(ctx: Context<CrankSubscription>) -> Result<()> {
+        require!(
+            ctx.accounts.agent.is_bonded(&ctx.accounts.registry),
+            AgentRegistryError::UnderBonded
+        );
+        require!(!ctx.accounts.agent.compromised, AgentRegistryError::AgentCompromised);
+        let subscription = &mut ctx.accounts.subscription;
+        require!(
+            subscription.materialized < subscription.periods,
+            AgentRegistryError::SubscriptionExhausted
+        );
+        let period_start = subscription.period_start(subscription.materialized)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= period_start, AgentRegistryError::DeadlineNotReached);
+
+        let request = &mut ctx.accounts.proof_request;
+        request.agent = subscription.agent;
+        request.market_id = subscription.market_id;
+        request.requester = subscription.requester;
+        request.requested_at = now;
+        request.deposit = 0;
+        request.signer = Pubkey::default();
+        request.deadline_ts = period_start
+            .checked_add(subscription.interval_secs)
+            .ok_or(AgentRegistryError::Overflow)?;
+        request.fulfilled = false;
+        request.slashable = true;
+        request.subscription_open = true;
+        request.proof_uri = String::new();
+        request.log_root = [0u8; 32];
+        request.bump = ctx.bumps.proof_request;
+
+        subscription.materialized += 1;
+        let agent = &mut ctx.accounts.agent;
+        agent.open_subscription_requests = agent
+            .open_subscription_requests
+            .checked_add(1)
+            .ok_or(AgentRegistryError::Overflow)?;
+
+        let rent = Rent::get()?.minimum_balance(8 + ProofRequest::INIT_SPACE);
+        subscription.sub_lamports(rent)?;
+        ctx.accounts.cranker.add_lamports(rent)?;
+
+        emit!(RequestProofEvent {
+            agent: subscription.agent,
+            market_id: subscription.market_id,
+            deadline_ts: request.deadline_ts,
+            request: request.key()
+        });
+
+        Ok(())
+    }
+
+    /// Stop a subscription; unused prepaid rent returns to the requester.
+    pub fn cancel_subscription(_ctx: Context<CancelSubscription>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Open a bond-weighted resolution round for a market. Registered agents
+    /// report a verdict until `window_end_ts`. The market account itself must
+    /// sign, so only the program owning it (via its PDA) can open its round;
+    /// the prediction program checks the window against the market's close.
+    /// A round that finalized without an outcome may be reopened; it moves to
+    /// a new epoch so every agent can vote again.
+    pub fn open_resolution_round(ctx: Context<OpenResolutionRound>, window_end_ts: i64) -> Result<()> {
+        require_gte!(window_end_ts, Clock::get()?.unix_timestamp, AgentRegistryError::DeadlineInPast);
+
+        let market_id = ctx.accounts.market.key().to_bytes();
+        let round = &mut ctx.accounts.round;
+        if round.market_id == market_id {
+            require!(round.finalized && round.outcome.is_none(), AgentRegistryError::RoundAlreadyOpen);
+            round.epoch = round.epoch.checked_add(1).ok_or(AgentRegistryError::Overflow)?;
+        } else {
+            round.epoch = 0;
+        }
+        round.market_id = market_id;
+        round.opened_by = ctx.accounts.payer.key();
+        round.window_end_ts = window_end_ts;
+        round.yes_weight = 0;
+        round.no_weight = 0;
+        round.submissions = 0;
+        round.finalized = false;
+        round.outcome = None;
+        round.bump = ctx.bumps.round;
+
+        emit!(ResolutionRoundOpened {
+            round: round.key(),
+            market_id,
+            window_end_ts,
+        });
+
+        Ok(())
+    }
+
+    pub fn submit_outcome(ctx: Context<SubmitOutcome>, verdict: Verdict) -> Result<()> {
+        let agent = &mut ctx.accounts.agent;
+        require!(!agent.compromised, AgentRegistryError::AgentCompromised);
+        require!(agent.is_bonded(&ctx.accounts.registry), AgentRegistryError::UnderBonded);
+
+        let round = &mut ctx.accounts.round;
+        require!(!round.finalized, AgentRegistryError::RoundFinalized);
+        require!(
+            Clock::get()?.unix_timestamp <= round.window_end_ts,
+            AgentRegistryError::SubmissionWindowClosed
+        );
+
+        // Votes are weighted by the bond the agent has at stake
+        let weight = agent.bond_lamports;
+        match verdict {
+            Verdict::Yes => round.yes_weight = round.yes_weight.checked_add(weight).ok_or(AgentRegistryError::Overflow)?,
+            Verdict::No => round.no_weight = round.no_weight.checked_add(weight).ok_or(AgentRegistryError::Overflow)?,
+        }
+        round.submissions = round.submissions.checked_add(1).ok_or(AgentRegistryError::Overflow)?;
+        agent.open_votes = agent.open_votes.checked_add(1).ok_or(AgentRegistryError::Overflow)?;
+
+        let vote = &mut ctx.accounts.vote;
+        vote.round = round.key();
+        vote.agent = agent.key();
+        vote.epoch = round.epoch;
+        vote.verdict = verdict;
+        vote.weight = weight;
+        vote.slashed = false;
+        vote.settled = false;
+        vote.bump = ctx.bumps.vote;
+
+        emit!(OutcomeSubmitted {
+            round: round.key(),
+            agent: agent.key(),
+            verdict,
+            weight,
+        });
+
+        Ok(())
+    }
+
+    /// Close the submission window. The heavier side wins; a tie, or fewer
+    /// votes than the registry's quorum, leaves the round without an outcome
+    /// and nobody is slashable.
+    pub fn finalize_round(ctx: Context<FinalizeRound>) -> Result<()> {
+        let round = &mut ctx.accounts.round;
+        // Cranks are retried on flaky RPCs; finishing work already done is a no-op
+        if round.finalized {
+            return Ok(());
+        }
+        require!(
+            Clock::get()?.unix_timestamp > round.window_end_ts,
+            AgentRegistryError::DeadlineNotReached
+        );
+
+        round.finalized = true;
+        round.outcome = if round.submissions < ctx.accounts.registry.round_quorum {
+            None
+        } else {
+            match round.yes_weight.cmp(&round.no_weight) {
+                std::cmp::Ordering::Greater => Some(Verdict::Yes),
+                std::cmp::Ordering::Less => Some(Verdict::No),
+                std::cmp::Ordering::Equal => None,
+            }
+        };
+
+        emit!(ResolutionRoundFinalized {
+            round: round.key(),
+            market_id: round.market_id,
+            outcome: round.outcome,
+            yes_weight: round.yes_weight,
+            no_weight: round.no_weight,
+        });
+
+        Ok(())
+    }
+
+    /// Release the bond behind a vote that can't be slashed: it agreed with
+    /// the outcome, its round ended without one, or that round was since
+    /// reopened. Permissionless; withdraw_bond waits on every open vote.
+    pub fn release_vote(ctx: Context<ReleaseVote>) -> Result<()> {
+        let round = &ctx.accounts.round;
+        let vote = &mut ctx.accounts.vote;
+        if vote.settled {
+            return Ok(());
+        }
+        if vote.epoch == round.epoch {
+            require!(round.finalized, AgentRegistryError::RoundNotFinalized);
+            require!(
+                !round.outcome.is_some_and(|outcome| outcome != vote.verdict),
+                AgentRegistryError::VoteSlashable
+            );
+        }
+
+        vote.settled = true;
+        let agent = &mut ctx.accounts.agent;
+        agent.open_votes = agent.open_votes.saturating_sub(1);
+
+        Ok(())
+    }
+
+    /// Slash an agent whose verdict lost a finalized round. The penalty is
+    /// capped at what the vault holds above its rent reserve.
+    pub fn slash_dissenter(ctx: Context<SlashDissenter>) -> Result<()> {
+        let registry = &ctx.accounts.registry;
+
+        let round = &ctx.accounts.round;
+        let vote = &mut ctx.accounts.vote;
+        require!(round.finalized, AgentRegistryError::RoundNotFinalized);
+        // Already slashed: a retried crank succeeds without slashing twice
+        if vote.slashed {
+            return Ok(());
+        }
+        require!(
+            round.outcome.is_some_and(|outcome| outcome != vote.verdict),
+            AgentRegistryError::NotSlashable
+        );
+
+        // Never more than the recorded bond, nor below the vault's rent reserve
+        let rent_reserve = Rent::get()?.minimum_balance(0);
+        let penalty = registry
+            .slash_penalty_lamports
+            .min(ctx.accounts.agent.bond_lamports)
+            .min(ctx.accounts.vault.lamports().saturating_sub(rent_reserve));
+
+        let bump = ctx.bumps.vault;
+        let agent_key = ctx.accounts.agent.key();
+        let vault_seeds: &[&[u8]] = &[
+            VAULT_SEED,
+            agent_key.as_ref(),
+            &[bump],
+        ];
+        let signer = &[vault_seeds];
+        transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.vault.to_account_info(),
+                    to: ctx.accounts.authority.to_account_info(),
+                },
+                signer,
+            ),
+            penalty,
+        )?;
+
+        vote.slashed = true;
+        vote.settled = true;
+        let agent = &mut ctx.accounts.agent;
+        agent.open_votes = agent.open_votes.saturating_sub(1);
+        // Vote weight, is_bonded and withdraw_bond all read the recorded bond
+        agent.bond_lamports = agent
+            .bond_lamports
+            .checked_sub(penalty)
+            .ok_or(AgentRegistryError::Overflow)?;
+
+        emit!(DissenterSlashed {
+            agent: agent_key,
+            round: round.key(),
+            market_id: round.market_id,
+            penalty,
+        });
+
+        Ok(())
+    }
+
+    /// Record (or rotate) the upgrade authority the deployed program is expected
+    /// to have. `None` means the program is expected to be immutable.
+    pub fn set_expected_upgrade_authority(
+        ctx: Context<SetExpectedUpgradeAuthority>,
+        expected: Option<Pubkey>,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.expected_upgrade_authority = expected;
+        Ok(())
+    }
+
+    /// Permissionless check that the program's current upgrade authority matches
+    /// the one recorded on the registry. Frontends can simulate this to show
+    /// whether the program is still upgradeable and by whom.
+    pub fn assert_upgrade_authority(ctx: Context<AssertUpgradeAuthority>) -> Result<()> {
+        let expected = ctx.accounts.registry.expected_upgrade_authority;
+        let actual = ctx.accounts.program_data.upgrade_authority_address;
+        check_program_upgrade_authority(&ctx.accounts.program_data, expected)?;
+
+        emit!(UpgradeAuthorityChecked {
+            expected,
+            actual,
+            last_deploy_slot: ctx.accounts.program_data.slot,
+        });
+
+        Ok(())
+    }
+}
+
+/// Fails unless the program data's upgrade authority equals `expected`.
+pub fn check_program_upgrade_authority(program_data: &ProgramData, expected: Option<Pubkey>) -> Result<()> {
+    require!(
+        program_data.upgrade_authority_address == expected,
+        AgentRegistryError::UpgradeAuthorityMismatch
+    );
+    Ok(())
+}
+
+/// Seed of the tag index for `tag`; hashed so any tag fits in a seed.
+pub fn tag_hash(tag: &str) -> [u8; 32] {
+    hash(tag.as_bytes()).to_bytes()
+}
+
+/// Move `agent` between tag indexes as its tags go from `old` to `new`.
+/// `indexes` holds the index of every dropped tag, then of every added one.
+fn sync_tag_indexes<'info>(
+    indexes: &'info [AccountInfo<'info>],
+    agent: Pubkey,
+    old: &[String],
+    new: &[String],
+) -> Result<()> {
+    let mut changes: Vec<(&String, bool)> = Vec::new();
+    for tag in old.iter().filter(|t| !new.contains(t)) {
+        if !changes.contains(&(tag, false)) {
+            changes.push((tag, false));
+        }
+    }
+    for tag in new.iter().filter(|t| !old.contains(t)) {
+        if !changes.contains(&(tag, true)) {
+            changes.push((tag, true));
+        }
+    }
+    require!(indexes.len() == changes.len(), AgentRegistryError::TagIndexMismatch);
+
+    for (info, (tag, add)) in indexes.iter().zip(changes) {
+        require!(info.is_writable, AgentRegistryError::TagIndexMismatch);
+        let mut index = Account::<TagIndex>::try_from(info)?;
+        require!(index.tag == *tag, AgentRegistryError::TagIndexMismatch);
+        if add {
+            require!(index.agents.len() < MAX_TAG_INDEX_AGENTS, AgentRegistryError::TagIndexFull);
+            index.agents.push(agent);
+        } else {
+            index.agents.retain(|a| *a != agent);
+        }
+        index.exit(&crate::ID)?;
+    }
+    Ok(())
+}
+
+fn validate_metadata(name: &str, url: &str, tags: &[String]) -> Result<()> {
+    require!(!name.is_empty() && name.len() <= MAX_NAME, AgentRegistryError::NameTooLong);
+    require!(!url.is_empty() && url.len() <= MAX_URL, AgentRegistryError::UrlTooLong);
+    require!(tags.len() <= MAX_TAGS, AgentRegistryError::TooManyTags);
+    for t in tags {
+        require!(!t.is_empty() && t.len() <= MAX_TAG_LEN, AgentRegistryError::TagTooLong);
+    }
+    Ok(())
+}
+
+// Accounts
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(init, payer = authority, seeds = [REGISTRY_SEED], bump, space = 8 + Registry::INIT_SPACE)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterAgent<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [AGENT_SEED, agent_wallet.key().as_ref()],
+        bump,
+        space = 8 + Agent::INIT_SPACE
+    )]
+    pub agent: Account<'info, Agent>,
+    /// CHECK: Agent wants funds to flow to this wallet; doesn't need to be signer.
+    pub agent_wallet: UncheckedAccount<'info>,
+    /// System-owned PDA escrowing the agent's bond
+    #[account(mut, seeds = [VAULT_SEED, agent.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tag: String)]
+pub struct InitTagIndex<'info> {
+    #[account(init, payer = payer, seeds = [TAG_SEED, tag_hash(&tag).as_ref()], bump, space = 8 + TagIndex::INIT_SPACE)]
+    pub tag_index: Account<'info, TagIndex>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AttestModel<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, constraint = agent.is_operator(&operator.key()) @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSigningKeys<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestProof<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init,
+        payer = requester,
+        seeds = [REQUEST_SEED, agent.key().as_ref()],
+        bump,
+        space = 8 + ProofRequest::INIT_SPACE
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    #[account(
+        init_if_needed,
+        payer = requester,
+        seeds = [REQUESTER_SEED, requester.key().as_ref()],
+        bump,
+        space = 8 + RequesterState::INIT_SPACE
+    )]
+    pub requester_state: Account<'info, RequesterState>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitProof<'info> {
+    #[account(
+        mut,
+        seeds = [AGENT_SEED, agent.agent_wallet.as_ref()],
+        bump = agent.bump,
+        constraint = submitter.key() == agent.authority || agent.is_operator(&submitter.key()) @ AgentRegistryError::Unauthorized
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut, has_one = agent @ AgentRegistryError::InvalidRequest)]
+    pub proof_request: Account<'info, ProofRequest>,
+    /// Agent authority, agent wallet or a registered signing key
+    pub submitter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MarkSlash<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    #[account(seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    // Direct requests and subscription periods use different seeds
+    #[account(has_one = agent @ AgentRegistryError::InvalidRequest)]
+    pub proof_request: Account<'info, ProofRequest>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [PENDING_SLASH_SEED, proof_request.key().as_ref()],
+        bump,
+        space = 8 + PendingSlash::INIT_SPACE
+    )]
+    pub pending_slash: Account<'info, PendingSlash>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VetoSlash<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        address = pending_slash.request @ AgentRegistryError::InvalidRequest,
+        has_one = agent @ AgentRegistryError::InvalidRequest
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [PENDING_SLASH_SEED, proof_request.key().as_ref()],
+        bump = pending_slash.bump
+    )]
+    pub pending_slash: Account<'info, PendingSlash>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSlash<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(mut, has_one = agent @ AgentRegistryError::InvalidRequest)]
+    pub proof_request: Account<'info, ProofRequest>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [PENDING_SLASH_SEED, proof_request.key().as_ref()],
+        bump = pending_slash.bump
+    )]
+    pub pending_slash: Account<'info, PendingSlash>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, agent.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    /// Registry authority receives penalties
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireRequest<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        close = requester,
+        has_one = agent @ AgentRegistryError::InvalidRequest,
+        has_one = requester @ AgentRegistryError::Unauthorized
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    /// CHECK: Must not exist; a marked slash has to be vetoed or finalized first
+    #[account(
+        seeds = [PENDING_SLASH_SEED, proof_request.key().as_ref()],
+        bump,
+        constraint = pending_slash.data_is_empty() @ AgentRegistryError::SlashPending
+    )]
+    pub pending_slash: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FlagCompromised<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        mut,
+        seeds = [AGENT_SEED, agent.agent_wallet.as_ref()],
+        bump = agent.bump,
+        constraint = signer.key() == registry.authority || signer.key() == agent.authority @ AgentRegistryError::Unauthorized
+    )]
+    pub agent: Account<'info, Agent>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequestLimits<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRequest<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        close = requester,
+        seeds = [REQUEST_SEED, agent.key().as_ref()],
+        bump = proof_request.bump,
+        has_one = agent @ AgentRegistryError::InvalidRequest,
+        has_one = requester @ AgentRegistryError::Unauthorized
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    /// CHECK: Must not exist; a marked slash has to be vetoed or finalized first
+    #[account(
+        seeds = [PENDING_SLASH_SEED, proof_request.key().as_ref()],
+        bump,
+        constraint = pending_slash.data_is_empty() @ AgentRegistryError::SlashPending
+    )]
+    pub pending_slash: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAppealWindow<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoundQuorum<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, agent.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeregisterAgent<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [AGENT_SEED, agent.agent_wallet.as_ref()],
+        bump = agent.bump,
+        has_one = authority @ AgentRegistryError::Unauthorized
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureTier<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TopUpBond<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    #[account(mut, seeds = [VAULT_SEED, agent.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreditEarnings<'info> {
+    #[account(seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [EARNINGS_SEED, agent.key().as_ref()],
+        bump,
+        space = 8 + AgentEarnings::INIT_SPACE
+    )]
+    pub earnings: Account<'info, AgentEarnings>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimEarnings<'info> {
+    #[account(seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized, has_one = agent_wallet @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    #[account(mut, seeds = [EARNINGS_SEED, agent.key().as_ref()], bump = earnings.bump)]
+    pub earnings: Account<'info, AgentEarnings>,
+    /// CHECK: Payout destination, pinned to the agent's wallet
+    #[account(mut)]
+    pub agent_wallet: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAgentTier<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: [u8; 32])]
+pub struct CreateSubscription<'info> {
+    #[account(seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        init,
+        payer = requester,
+        seeds = [SUBSCRIPTION_SEED, agent.key().as_ref(), market_id.as_ref()],
+        bump,
+        space = 8 + ProofSubscription::INIT_SPACE
+    )]
+    pub subscription: Account<'info, ProofSubscription>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.agent.as_ref(), subscription.market_id.as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, ProofSubscription>,
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, address = subscription.agent @ AgentRegistryError::InvalidRequest)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        init,
+        payer = cranker,
+        seeds = [REQUEST_SEED, subscription.key().as_ref(), &subscription.materialized.to_le_bytes()],
+        bump,
+        space = 8 + ProofRequest::INIT_SPACE
+    )]
+    pub proof_request: Account<'info, ProofRequest>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        close = requester,
+        seeds = [SUBSCRIPTION_SEED, subscription.agent.as_ref(), subscription.market_id.as_ref()],
+        bump = subscription.bump,
+        has_one = requester @ AgentRegistryError::Unauthorized
+    )]
+    pub subscription: Account<'info, ProofSubscription>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenResolutionRound<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [ROUND_SEED, market.key().as_ref()],
+        bump,
+        space = 8 + ResolutionRound::INIT_SPACE
+    )]
+    pub round: Account<'info, ResolutionRound>,
+    /// The market being resolved; a PDA signing through its owning program
+    pub market: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitOutcome<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [ROUND_SEED, round.market_id.as_ref()], bump = round.bump)]
+    pub round: Account<'info, ResolutionRound>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    // One vote per agent per round epoch
+    #[account(
+        init,
+        payer = authority,
+        seeds = [VOTE_SEED, round.key().as_ref(), agent.key().as_ref(), &round.epoch.to_le_bytes()],
+        bump,
+        space = 8 + OutcomeVote::INIT_SPACE
+    )]
+    pub vote: Account<'info, OutcomeVote>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeRound<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(mut, seeds = [ROUND_SEED, round.market_id.as_ref()], bump = round.bump)]
+    pub round: Account<'info, ResolutionRound>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseVote<'info> {
+    #[account(seeds = [ROUND_SEED, round.market_id.as_ref()], bump = round.bump)]
+    pub round: Account<'info, ResolutionRound>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
+    pub agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        seeds = [VOTE_SEED, round.key().as_ref(), agent.key().as_ref(), &vote.epoch.to_le_bytes()],
+        bump = vote.bump
+    )]
+    pub vote: Account<'info, OutcomeVote>,
+}
+
+#[derive(Accounts)]
+pub struct SlashDissenter<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    #[account(seeds = [ROUND_SEED, round.market_id.as_ref()], bump = round.bump)]
+    pub round: Account<'info, ResolutionRound>,
+    #[account(mut, seeds = [AGENT_SEED, agent.agent_wallet.as_ref()], bump = agent.bump)]
     pub agent: Account<'info, Agent>,
+    // Only votes from the round's current epoch count against its outcome
+    #[account(
+        mut,
+        seeds = [VOTE_SEED, round.key().as_ref(), agent.key().as_ref(), &round.epoch.to_le_bytes()],
+        bump = vote.bump
+    )]
+    pub vote: Account<'info, OutcomeVote>,
     #[account(
         mut,
         seeds = [VAULT_SEED, agent.key().as_ref()],
         bump
     )]
     pub vault: SystemAccount<'info>,
+    /// Registry authority receives penalties
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetExpectedUpgradeAuthority<'info> {
+    #[account(mut, seeds = [REGISTRY_SEED], bump = registry.bump, has_one = authority @ AgentRegistryError::Unauthorized)]
+    pub registry: Account<'info, Registry>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssertUpgradeAuthority<'info> {
+    #[account(seeds = [REGISTRY_SEED], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ AgentRegistryError::InvalidProgramData)]
+    pub program: Program<'info, crate::program::AgentRegistry>,
+    pub program_data: Account<'info, ProgramData>,
+}
+
 // Data structs
 #[account]
+#[derive(InitSpace)]
 pub struct Registry {
     pub authority: Pubkey,
     pub bond_lamports: u64,
     pub slash_penalty_lamports: u64,
+    pub expected_upgrade_authority: Option<Pubkey>,
+    /// Indexed by `AgentTier as usize`
+    pub tiers: [TierConfig; 3],
+    pub appeal_window_secs: i64,
+    /// Bumped whenever any tier's bond requirement changes
+    pub bond_version: u32,
+    /// Minimum gap between two request_proof calls from one requester
+    pub request_cooldown_secs: i64,
+    /// Refundable deposit locked in each direct proof request
+    pub request_deposit_lamports: u64,
+    /// Votes a resolution round needs to produce an outcome
+    pub round_quorum: u32,
     pub bump: u8,
 }
 
 impl Registry {
-    pub const LEN: usize = 32 + 8 + 8 + 1;
+    pub fn tier(&self, tier: AgentTier) -> &TierConfig {
+        &self.tiers[tier as usize]
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AgentTier {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TierConfig {
+    /// Bond an agent in this tier must keep in its vault
+    pub bond_lamports: u64,
+    /// Markets an agent in this tier may have open at once
+    pub max_open_markets: u16,
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct Agent {
     pub authority: Pubkey,
     pub agent_wallet: Pubkey,
+    #[max_len(MAX_NAME)]
     pub name: String,
+    #[max_len(MAX_URL)]
     pub url: String,
+    #[max_len(MAX_TAGS, MAX_TAG_LEN)]
     pub tags: Vec<String>,
     pub bond_lamports: u64,
+    pub tier: AgentTier,
+    /// Registry bond_version the agent last satisfied
+    pub bond_version: u32,
     pub request_count: u64,
+    /// Requests that expired with no proof, slash or veto
+    pub missed_requests: u32,
     pub pending_request: Option<Pubkey>,
+    /// Hash of the model/binary build, attested by the agent wallet
+    pub model_hash: [u8; 32],
+    #[max_len(MAX_ATTESTATION_URI)]
+    pub attestation_uri: String,
+    /// Keys reported leaked; markets about this agent may be frozen
+    pub compromised: bool,
+    /// Extra keys allowed to act as agent_wallet, for PDA-owned wallets
+    #[max_len(MAX_SIGNING_KEYS)]
+    pub signing_keys: Vec<Pubkey>,
+    /// Resolution votes not yet slashed or released; the bond stays locked meanwhile
+    pub open_votes: u32,
+    /// Subscription requests cranked but not yet fulfilled, slashed, vetoed
+    /// or expired; the bond stays locked meanwhile
+    pub open_subscription_requests: u32,
     pub bump: u8,
 }
 
 impl Agent {
-    pub const LEN: usize = 32 // authority
-        + 32 // agent_wallet
-        + 4 + MAX_NAME // name
-        + 4 + MAX_URL // url
-        + 4 + (MAX_TAGS * (4 + MAX_TAG_LEN)) // tags vec cap
-        + 8 // bond
-        + 8 // request_count
-        + 1 + 32 // option pubkey
-        + 1; // bump
+    /// Whether `key` operates the agent: its wallet or a registered signing key
+    pub fn is_operator(&self, key: &Pubkey) -> bool {
+        *key == self.agent_wallet || self.signing_keys.contains(key)
+    }
+
+    /// Whether the agent's bond covers its tier's current requirement.
+    pub fn is_bonded(&self, registry: &Registry) -> bool {
+        self.bond_lamports >= registry.tier(self.tier).bond_lamports
+    }
+
+    /// Release a settled subscription request's hold on the bond. Direct
+    /// requests and ones already released are left alone.
+    pub fn settle_subscription_request(&mut self, request: &mut ProofRequest) {
+        if request.subscription_open {
+            request.subscription_open = false;
+            self.open_subscription_requests = self.open_subscription_requests.saturating_sub(1);
+        }
+    }
+}
+
+/// Agents registered under one tag, at `["tag", tag_hash(tag)]`, so
+/// matchmakers can find e.g. every "rust-coder" agent without a scan.
+#[account]
+#[derive(InitSpace)]
+pub struct TagIndex {
+    #[max_len(MAX_TAG_LEN)]
+    pub tag: String,
+    #[max_len(MAX_TAG_INDEX_AGENTS)]
+    pub agents: Vec<Pubkey>,
+    pub bump: u8,
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct ProofRequest {
     pub agent: Pubkey,
     pub requester: Pubkey,
     pub market_id: [u8; 32],
     pub requested_at: i64,
     pub deadline_ts: i64,
+    #[max_len(MAX_PROOF_URI)]
     pub proof_uri: String,
     pub log_root: [u8; 32],
     pub signature: [u8; 64],
+    /// Key that submitted the proof; `signature` verifies against it
+    pub signer: Pubkey,
     pub fulfilled: bool,
     pub slashable: bool,
+    /// Cranked from a subscription and still counted in the agent's
+    /// open_subscription_requests
+    pub subscription_open: bool,
+    /// Lamports above rent, returned with the rent on close
+    pub deposit: u64,
+    pub bump: u8,
+}
+
+/// Lamports owed to an agent, at `["earnings", agent]`. The balance above
+/// rent is claimable; the totals are lifetime and never decrease.
+#[account]
+#[derive(InitSpace)]
+pub struct AgentEarnings {
+    pub agent: Pubkey,
+    pub request_fees: u64,
+    pub prize_shares: u64,
+    pub creation_rebates: u64,
+    pub claimed: u64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EarningsSource {
+    RequestFee,
+    PrizeShare,
+    CreationRebate,
+}
+
+/// Per-requester rate limit state for request_proof
+#[account]
+#[derive(InitSpace)]
+pub struct RequesterState {
+    pub last_request_ts: i64,
+    pub bump: u8,
+}
+
+/// Standing order for one proof request per period. Period `k` is requested
+/// at `["request", subscription, k (u32 LE)]` and is due one interval later.
+#[account]
+#[derive(InitSpace)]
+pub struct ProofSubscription {
+    pub agent: Pubkey,
+    pub requester: Pubkey,
+    pub market_id: [u8; 32],
+    pub start_ts: i64,
+    pub interval_secs: i64,
+    pub periods: u32,
+    pub materialized: u32,
+    pub bump: u8,
+}
+
+impl ProofSubscription {
+    pub fn period_start(&self, period: u32) -> Result<i64> {
+        self.interval_secs
+            .checked_mul(period as i64)
+            .and_then(|offset| self.start_ts.checked_add(offset))
+            .ok_or(error!(AgentRegistryError::Overflow))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Verdict {
+    Yes,
+    No,
+}
+
+/// Bond-weighted vote on a market's outcome. `market_id` is the market
+/// account's pubkey bytes so the market program can look the round up.
+#[account]
+#[derive(InitSpace)]
+pub struct ResolutionRound {
+    pub market_id: [u8; 32],
+    pub opened_by: Pubkey,
+    pub window_end_ts: i64,
+    pub yes_weight: u64,
+    pub no_weight: u64,
+    pub submissions: u32,
+    pub finalized: bool,
+    pub outcome: Option<Verdict>,
+    /// Times the round was reopened after ending without an outcome
+    pub epoch: u32,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OutcomeVote {
+    pub round: Pubkey,
+    pub agent: Pubkey,
+    /// Round epoch the vote was cast in
+    pub epoch: u32,
+    pub verdict: Verdict,
+    pub weight: u64,
+    pub slashed: bool,
+    /// Slashed or released; no longer counted in the agent's open_votes
+    pub settled: bool,
     pub bump: u8,
 }
 
-impl ProofRequest {
-    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 4 + MAX_PROOF_URI + 32 + 64 + 1 + 1 + 1;
+/// Slash awaiting its appeal window; closing it (veto or finalize) refunds rent
+#[account]
+#[derive(InitSpace)]
+pub struct PendingSlash {
+    pub agent: Pubkey,
+    pub request: Pubkey,
+    pub penalty: u64,
+    pub appeal_ends_ts: i64,
+    pub bump: u8,
 }
 
 // Events consumed by frontend/agent server
+#[event]
+pub struct AgentRegistered {
+    pub agent: Pubkey,
+    pub authority: Pubkey,
+    pub agent_wallet: Pubkey,
+    pub name: String,
+    pub url: String,
+    pub tags: Vec<String>,
+    pub bond_lamports: u64,
+}
+
+#[event]
+pub struct AgentMetadataUpdated {
+    pub agent: Pubkey,
+    pub name: String,
+    pub url: String,
+    pub tags: Vec<String>,
+}
+
+#[event]
+pub struct BondWithdrawn {
+    pub agent: Pubkey,
+    pub authority: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct AgentDeregistered {
+    pub agent: Pubkey,
+    pub authority: Pubkey,
+    pub agent_wallet: Pubkey,
+}
+
 #[event]
 pub struct RequestProofEvent {
     pub agent: Pubkey,
@@ -451,6 +1953,27 @@ pub struct ProofSubmitted {
     pub request: Pubkey,
     pub proof_uri: String,
     pub log_root: [u8; 32],
+    pub model_hash: [u8; 32],
+    pub signer: Pubkey,
+}
+
+#[event]
+pub struct AgentCompromised {
+    pub agent: Pubkey,
+    pub flagged_by: Pubkey,
+}
+
+#[event]
+pub struct SigningKeysSet {
+    pub agent: Pubkey,
+    pub signing_keys: Vec<Pubkey>,
+}
+
+#[event]
+pub struct ModelAttested {
+    pub agent: Pubkey,
+    pub model_hash: [u8; 32],
+    pub attestation_uri: String,
 }
 
 #[event]
@@ -461,6 +1984,114 @@ pub struct AgentSlashed {
     pub penalty: u64,
 }
 
+#[event]
+pub struct BondRequirementChanged {
+    pub tier: AgentTier,
+    pub bond_lamports: u64,
+    pub bond_version: u32,
+}
+
+#[event]
+pub struct BondToppedUp {
+    pub agent: Pubkey,
+    pub amount: u64,
+    pub bond_lamports: u64,
+    pub bond_version: u32,
+}
+
+#[event]
+pub struct EarningsCredited {
+    pub agent: Pubkey,
+    pub source: EarningsSource,
+    pub amount: u64,
+    pub payer: Pubkey,
+}
+
+#[event]
+pub struct EarningsClaimed {
+    pub agent: Pubkey,
+    pub agent_wallet: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+}
+
+#[event]
+pub struct RequestClosed {
+    pub agent: Pubkey,
+    pub request: Pubkey,
+    pub requester: Pubkey,
+    pub deposit: u64,
+}
+
+#[event]
+pub struct RequestExpired {
+    pub agent: Pubkey,
+    pub request: Pubkey,
+    pub market_id: [u8; 32],
+    pub requester: Pubkey,
+}
+
+#[event]
+pub struct SlashMarked {
+    pub agent: Pubkey,
+    pub request: Pubkey,
+    pub penalty: u64,
+    pub appeal_ends_ts: i64,
+}
+
+#[event]
+pub struct SlashVetoed {
+    pub agent: Pubkey,
+    pub request: Pubkey,
+}
+
+#[event]
+pub struct AgentTierChanged {
+    pub agent: Pubkey,
+    pub previous: AgentTier,
+    pub tier: AgentTier,
+    pub required_bond_lamports: u64,
+}
+
+#[event]
+pub struct ResolutionRoundOpened {
+    pub round: Pubkey,
+    pub market_id: [u8; 32],
+    pub window_end_ts: i64,
+}
+
+#[event]
+pub struct OutcomeSubmitted {
+    pub round: Pubkey,
+    pub agent: Pubkey,
+    pub verdict: Verdict,
+    pub weight: u64,
+}
+
+#[event]
+pub struct ResolutionRoundFinalized {
+    pub round: Pubkey,
+    pub market_id: [u8; 32],
+    pub outcome: Option<Verdict>,
+    pub yes_weight: u64,
+    pub no_weight: u64,
+}
+
+#[event]
+pub struct DissenterSlashed {
+    pub agent: Pubkey,
+    pub round: Pubkey,
+    pub market_id: [u8; 32],
+    pub penalty: u64,
+}
+
+#[event]
+pub struct UpgradeAuthorityChecked {
+    pub expected: Option<Pubkey>,
+    pub actual: Option<Pubkey>,
+    pub last_deploy_slot: u64,
+}
+
 // Errors
 #[error_code]
 pub enum AgentRegistryError {
@@ -492,4 +2123,116 @@ pub enum AgentRegistryError {
     InsufficientVaultBalance,
     #[msg("Proof URI too long")]
     ProofUriTooLong,
+    #[msg("Attestation URI too long")]
+    AttestationUriTooLong,
+    #[msg("Agent has been flagged as compromised")]
+    AgentCompromised,
+    #[msg("Bond is outside the range allowed on this cluster")]
+    BondOutOfRange,
+    #[msg("Program data account does not belong to this program")]
+    InvalidProgramData,
+    #[msg("Program upgrade authority does not match the registry")]
+    UpgradeAuthorityMismatch,
+    #[msg("Resolution round already finalized")]
+    RoundFinalized,
+    #[msg("Resolution round not finalized")]
+    RoundNotFinalized,
+    #[msg("Outcome submission window has closed")]
+    SubmissionWindowClosed,
+    #[msg("Subscription needs a positive interval and period count")]
+    InvalidSubscription,
+    #[msg("All subscription periods have been requested")]
+    SubscriptionExhausted,
+    #[msg("Agent bond is below its tier requirement")]
+    UnderBonded,
+    #[msg("Slash appeal window is still open")]
+    AppealWindowOpen,
+    #[msg("Appeal window cannot be negative")]
+    InvalidAppealWindow,
+    #[msg("Bond amount must be positive")]
+    InvalidBondAmount,
+    #[msg("Request has a pending slash")]
+    SlashPending,
+    #[msg("Withdraw the bond before deregistering")]
+    BondNotWithdrawn,
+    #[msg("Requester is still in its request cooldown")]
+    RequestCooldown,
+    #[msg("Request cooldown cannot be negative")]
+    InvalidCooldown,
+    #[msg("Request is still awaiting a proof or slash")]
+    RequestNotSettled,
+    #[msg("Earnings amount must be positive")]
+    InvalidEarningsAmount,
+    #[msg("No earnings to claim")]
+    NothingToClaim,
+    #[msg("Too many signing keys")]
+    TooManySigningKeys,
+    #[msg("Tag index accounts don't match the agent's tag changes")]
+    TagIndexMismatch,
+    #[msg("Tag index is full")]
+    TagIndexFull,
+    #[msg("Quorum must be at least one vote")]
+    InvalidQuorum,
+    #[msg("Round is already open or has an outcome")]
+    RoundAlreadyOpen,
+    #[msg("Agent has votes in unsettled rounds")]
+    VotePending,
+    #[msg("Vote lost the round and must be slashed")]
+    VoteSlashable,
+    #[msg("Agent has unsettled subscription requests")]
+    SubscriptionRequestsOutstanding,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{Bumps, InstructionData};
+    use std::collections::BTreeSet;
+
+    /// Run an accounts struct's validation on an instruction's encoded
+    /// arguments and no accounts. Anchor decodes the `#[instruction(...)]`
+    /// arguments before touching any account, so this fails on the missing
+    /// accounts exactly when the attribute agrees with the handler's signature.
+    macro_rules! validate_without_accounts {
+        ($accounts:ident, $ix:expr) => {{
+            let data = InstructionData::data(&$ix);
+            let mut accounts: &[AccountInfo] = &[];
+            let mut bumps = <$accounts as Bumps>::Bumps::default();
+            let mut reallocs = BTreeSet::new();
+            $accounts::try_accounts(&ID, &mut accounts, &data[8..], &mut bumps, &mut reallocs)
+                .err()
+                .expect("validation passed without accounts")
+        }};
+    }
+
+    #[test]
+    fn instruction_attributes_match_handler_args() {
+        let not_enough_keys: Error = ErrorCode::AccountNotEnoughKeys.into();
+        assert_eq!(
+            validate_without_accounts!(
+                OpenResolutionRound,
+                instruction::OpenResolutionRound { window_end_ts: 1 }
+            ),
+            not_enough_keys
+        );
+        assert_eq!(
+            validate_without_accounts!(
+                InitTagIndex,
+                instruction::InitTagIndex { tag: "rust-coder".to_string() }
+            ),
+            not_enough_keys
+        );
+        assert_eq!(
+            validate_without_accounts!(
+                CreateSubscription,
+                instruction::CreateSubscription {
+                    market_id: [1; 32],
+                    start_ts: 2,
+                    interval_secs: 3,
+                    periods: 4,
+                }
+            ),
+            not_enough_keys
+        );
+    }
 }